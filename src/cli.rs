@@ -0,0 +1,115 @@
+// src/cli.rs
+
+use std::{env, path::PathBuf};
+
+/// Options parsed from the command line.
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    /// Auto-accept every confirmation prompt.
+    pub assume_yes: bool,
+    /// Stop at the ends of the menu instead of wrapping around.
+    pub no_wrap: bool,
+    /// Draw with plain ASCII instead of Unicode box-drawing and emoji.
+    pub ascii: bool,
+    /// Run the script on this `user@host` over SSH instead of locally.
+    pub deploy: Option<String>,
+    /// Record key events, state changes and script sizes to this file.
+    pub log_file: Option<PathBuf>,
+    /// Read a JSON selection profile from stdin instead of starting the TUI.
+    pub stdin_profile: bool,
+    /// With a profile, run the script instead of printing it.
+    pub run: bool,
+    /// Show stable item ids next to item names.
+    pub debug: bool,
+    /// Start with nothing selected instead of the distro's default repositories.
+    pub no_defaults: bool,
+    /// Proxy URL for the generated script and the dnf probes.
+    pub proxy: Option<String>,
+    /// TOML file of item names to select on startup; [p] on the review screen writes it.
+    pub profile: Option<PathBuf>,
+}
+
+pub const USAGE: &str = "Usage: el-init [OPTIONS]
+
+Options:
+  -y, --yes            Skip all confirmation prompts
+      --no-wrap        Stop at the ends of the menu instead of wrapping around
+      --ascii          Use ASCII-only rendering (automatic when TERM is linux or dumb)
+      --deploy HOST    Run the script on HOST (user@host) over SSH instead of locally
+      --log-file PATH  Write a debug trace of the session to PATH
+      --proxy URL      Route dnf, flatpak and downloads through the proxy at URL
+                       (http://, https:// or socks5://)
+      --profile PATH   Select the items named in the TOML file PATH on startup
+                       ([p] on the review screen saves the selection there)
+      --debug          Show item ids in the menu ([I] toggles them)
+      --no-defaults    Start with nothing selected (CRB and EPEL are pre-selected otherwise)
+      --stdin          Read a JSON profile from stdin and print its script without the TUI
+      --run            With --stdin, run the script instead of printing it
+      --check-distro   Print the detected distro and major version, then exit
+                       (exit code 1 if the distro is not supported)
+      --json           With --check-distro, print JSON instead of plain text
+      --diff-profiles A B
+                       Print the item ids selected in only one of two profile files, then exit
+                       (exit code 1 if they differ)
+  -h, --help           Print this help";
+
+/// What `main` should do after parsing the command line.
+pub enum CliCommand {
+    Run(CliArgs),
+    CheckDistro { json: bool },
+    DiffProfiles { a: PathBuf, b: PathBuf },
+    PrintHelp,
+}
+
+/// Parses the process arguments into a `CliCommand`.
+pub fn parse_args() -> Result<CliCommand, String> {
+    let mut args = CliArgs::default();
+    let mut check_distro = false;
+    let mut json = false;
+    let mut argv = env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "--check-distro" => check_distro = true,
+            "--diff-profiles" => match (argv.next(), argv.next()) {
+                (Some(a), Some(b)) => return Ok(CliCommand::DiffProfiles { a: PathBuf::from(a), b: PathBuf::from(b) }),
+                _ => return Err("--diff-profiles requires two profile files".to_string()),
+            },
+            "--json" => json = true,
+            "-y" | "--yes" => args.assume_yes = true,
+            "--no-wrap" => args.no_wrap = true,
+            "--ascii" => args.ascii = true,
+            "--deploy" => match argv.next() {
+                Some(host) if !host.starts_with('-') => args.deploy = Some(host),
+                _ => return Err("--deploy requires a host, e.g. --deploy admin@server".to_string()),
+            },
+            "--log-file" => match argv.next() {
+                Some(path) => args.log_file = Some(PathBuf::from(path)),
+                None => return Err("--log-file requires a path".to_string()),
+            },
+            "--proxy" => match argv.next() {
+                Some(url) => args.proxy = Some(crate::scripts::validate_proxy(&url)?),
+                None => return Err("--proxy requires a URL, e.g. --proxy http://proxy:3128".to_string()),
+            },
+            "--profile" => match argv.next() {
+                Some(path) => args.profile = Some(PathBuf::from(path)),
+                None => return Err("--profile requires a path to a TOML file".to_string()),
+            },
+            "--stdin" => args.stdin_profile = true,
+            "--run" => args.run = true,
+            "--debug" => args.debug = true,
+            "--no-defaults" => args.no_defaults = true,
+            "-h" | "--help" => return Ok(CliCommand::PrintHelp),
+            other => return Err(format!("unknown argument '{}'", other)),
+        }
+    }
+    if check_distro {
+        return Ok(CliCommand::CheckDistro { json });
+    }
+    if json {
+        return Err("--json can only be used with --check-distro".to_string());
+    }
+    if args.run && !args.stdin_profile {
+        return Err("--run needs a profile, e.g. --stdin".to_string());
+    }
+    Ok(CliCommand::Run(args))
+}
@@ -0,0 +1,78 @@
+// src/clipboard.rs
+
+use std::{
+    env,
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+/// Clipboard tools tried in order, with the environment variable that must be set for each.
+const TOOLS: &[(&str, &[&str], Option<&str>)] = &[
+    ("wl-copy", &[], Some("WAYLAND_DISPLAY")),
+    ("xclip", &["-selection", "clipboard"], Some("DISPLAY")),
+    ("xsel", &["--clipboard", "--input"], Some("DISPLAY")),
+    ("pbcopy", &[], None),
+];
+
+/// Copies `text` to the clipboard and returns where it went. Without a desktop session the
+/// text is sent to the terminal as an OSC 52 sequence, which most terminal emulators (including
+/// over SSH and inside tmux with `set-clipboard on`) place on the local clipboard.
+pub fn copy(text: &str) -> io::Result<&'static str> {
+    for (tool, args, display) in TOOLS {
+        if display.is_some_and(|var| env::var_os(var).is_none_or(|value| value.is_empty())) {
+            continue;
+        }
+        if pipe_to(tool, args, text).unwrap_or(false) {
+            return Ok(tool);
+        }
+    }
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()?;
+    Ok("terminal (OSC 52)")
+}
+
+/// Runs `tool` with `text` on stdin; `Ok(false)` when it exits unsuccessfully.
+fn pipe_to(tool: &str, args: &[&str], text: &str) -> io::Result<bool> {
+    let mut child = Command::new(tool).args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    Ok(child.wait()?.success())
+}
+
+/// Standard base64 with padding, as OSC 52 expects.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"sudo dnf install -y epel-release\n"), "c3VkbyBkbmYgaW5zdGFsbCAteSBlcGVsLXJlbGVhc2UK");
+    }
+}
@@ -0,0 +1,82 @@
+// src/deps.rs
+
+use std::collections::HashSet;
+
+/// Orders the selected ids so that every item comes after the items it requires, adding
+/// unselected prerequisites along the way unless `may_add` rejects them. Each entry is
+/// `(id, auto_added)`. Returns the offending chain, e.g. `["a", "b", "a"]`, if the
+/// requirements form a cycle.
+pub fn resolve(
+    selected: &[&'static str],
+    requires: impl Fn(&str) -> &'static [&'static str],
+    may_add: impl Fn(&str) -> bool,
+) -> Result<Vec<(&'static str, bool)>, Vec<&'static str>> {
+    let mut order = Vec::new();
+    let mut done = HashSet::new();
+    let mut stack = Vec::new();
+    for id in selected {
+        visit(id, selected, &requires, &may_add, &mut stack, &mut done, &mut order)?;
+    }
+    Ok(order)
+}
+
+fn visit(
+    id: &'static str,
+    selected: &[&'static str],
+    requires: &impl Fn(&str) -> &'static [&'static str],
+    may_add: &impl Fn(&str) -> bool,
+    stack: &mut Vec<&'static str>,
+    done: &mut HashSet<&'static str>,
+    order: &mut Vec<(&'static str, bool)>,
+) -> Result<(), Vec<&'static str>> {
+    if done.contains(id) || (!selected.contains(&id) && !may_add(id)) {
+        return Ok(());
+    }
+    if let Some(start) = stack.iter().position(|entry| *entry == id) {
+        let mut cycle = stack[start..].to_vec();
+        cycle.push(id);
+        return Err(cycle);
+    }
+    stack.push(id);
+    for required in requires(id) {
+        visit(required, selected, requires, may_add, stack, done, order)?;
+    }
+    stack.pop();
+    done.insert(id);
+    order.push((id, !selected.contains(&id)));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requires(id: &str) -> &'static [&'static str] {
+        match id {
+            "machines" => &["cockpit", "kvm"],
+            "cockpit" => &["base"],
+            "a" => &["b"],
+            "b" => &["a"],
+            _ => &[],
+        }
+    }
+
+    #[test]
+    fn prerequisites_come_first_and_are_marked() {
+        let order = resolve(&["machines", "kvm"], requires, |_| true).unwrap();
+        assert_eq!(order, vec![("base", true), ("cockpit", true), ("kvm", false), ("machines", false)]);
+    }
+
+    #[test]
+    fn rejected_prerequisites_are_left_out() {
+        let order = resolve(&["machines"], requires, |id| id != "cockpit").unwrap();
+        assert_eq!(order, vec![("kvm", true), ("machines", false)]);
+        let order = resolve(&["machines", "cockpit"], requires, |id| id != "cockpit").unwrap();
+        assert_eq!(order, vec![("base", true), ("cockpit", false), ("kvm", true), ("machines", false)]);
+    }
+
+    #[test]
+    fn cycles_are_reported() {
+        assert_eq!(resolve(&["a"], requires, |_| true), Err(vec!["a", "b", "a"]));
+    }
+}
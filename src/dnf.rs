@@ -0,0 +1,310 @@
+// src/dnf.rs
+
+use std::{collections::HashSet, io, process::{Command, Stdio}};
+
+/// One titled block of a `dnf group info` listing, e.g. "Mandatory Packages".
+#[derive(Debug, PartialEq)]
+pub struct GroupSection {
+    pub title: String,
+    pub members: Vec<String>,
+}
+
+/// Extracts the group name from a script that uses `groupinstall` or `group install`.
+pub fn group_name(script: &str) -> Option<String> {
+    for line in script.lines() {
+        let Some((_, rest)) = line.split_once("groupinstall").or_else(|| line.split_once("group install")) else {
+            continue;
+        };
+        let name: Vec<&str> = rest.split_whitespace().filter(|arg| !arg.starts_with('-')).collect();
+        if !name.is_empty() {
+            return Some(name.join(" ").trim_matches(|c| c == '\'' || c == '"').to_string());
+        }
+    }
+    None
+}
+
+/// Appends `--exclude` for the given package globs to every group install in the script.
+/// The globs are quoted so the shell leaves them for dnf to expand.
+pub fn with_group_excludes(script: &str, excludes: &[String]) -> String {
+    let exclude = format!(" --exclude='{}'", excludes.join(","));
+    script.lines()
+        .map(|line| {
+            if line.contains("groupinstall") || line.contains("group install") {
+                format!("{}{}", line, exclude)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Replaces every group install in the script with a plain install of the chosen members,
+/// through the same package manager. Members are quoted, since group names ("@Development
+/// Tools") may contain spaces.
+pub fn with_group_members(script: &str, members: &[String]) -> String {
+    let specs: Vec<String> = members.iter().map(|member| format!("'{}'", member)).collect();
+    script.lines()
+        .map(|line| match line.split_once("groupinstall").or_else(|| line.split_once("group install")) {
+            // The part before the subcommand is the package manager, with its sudo.
+            Some((command, _)) => format!("{}install -y {}", command, specs.join(" ")),
+            None => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Adds `flags` (e.g. "--allowerasing") right after the subcommand of every dnf install and
+/// group install in the script, ahead of any operators that may follow the package list.
+pub fn with_install_flags(script: &str, flags: &[&str]) -> String {
+    if flags.is_empty() {
+        return script.to_string();
+    }
+    script.lines()
+        .map(|line| {
+            let subcommand = ["dnf install", "dnf groupinstall", "dnf group install"].iter()
+                .find_map(|command| line.find(command).map(|start| start + command.len()));
+            match subcommand {
+                Some(end) => format!("{} {}{}", &line[..end], flags.join(" "), &line[end..]),
+                None => line.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Returns the ids of the repositories enabled on this system, from the local metadata cache.
+pub fn enabled_repos() -> Result<HashSet<String>, String> {
+    let output = Command::new("dnf").args(["-q", "-C", "repolist", "--enabled"])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| format!("Failed to run dnf: {}", err))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|id| *id != "repo")
+        .map(str::to_string)
+        .collect())
+}
+
+/// The package specs a script installs with `dnf install` (or `yum`/`microdnf install`), in
+/// order and without options or quotes. Group specs ("@Name") are included; `groupinstall`
+/// lines are not.
+pub fn install_specs(script: &str) -> Vec<String> {
+    let mut specs = Vec::new();
+    for line in script.lines() {
+        let Some((_, rest)) = line.split_once("dnf install").or_else(|| line.split_once("yum install")) else { continue };
+        for word in shell_words(rest) {
+            if matches!(word.as_str(), "&&" | "||" | ";" | "|") || word.starts_with('>') {
+                break;
+            }
+            if !word.starts_with('-') && !specs.contains(&word) {
+                specs.push(word);
+            }
+        }
+    }
+    specs
+}
+
+/// Splits on whitespace outside single or double quotes and drops the quotes.
+fn shell_words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quote = None;
+    for c in text.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => quote = Some(c),
+            (None, c) if c.is_whitespace() => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            (None, c) => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Returns which of the given package names are installed, according to rpm.
+pub fn installed_packages(names: &[String]) -> Result<HashSet<String>, String> {
+    if names.is_empty() {
+        return Ok(HashSet::new());
+    }
+    // rpm exits nonzero when any package is missing; each missing one gets a sentence instead.
+    let output = Command::new("rpm").args(["-q", "--qf", "%{NAME}\\n", "--"]).args(names)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| format!("Failed to run rpm: {}", err))?;
+    Ok(String::from_utf8_lossy(&output.stdout).lines()
+        .map(str::trim)
+        .filter(|line| !line.contains(' ') && names.iter().any(|name| name == line))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Returns the repository ids a script enables, if every line of it is a repo enable.
+/// COPRs map to dnf's `copr:copr.fedorainfracloud.org:owner:project` ids.
+pub fn enabled_by(script: &str) -> Option<Vec<String>> {
+    let mut ids = Vec::new();
+    for line in script.lines() {
+        if let Some(repo) = config_manager_repo(line) {
+            ids.push(repo.to_string());
+        } else if let Some((_, project)) = line.split_once("copr enable -y ") {
+            let (owner, name) = project.trim().split_once('/')?;
+            ids.push(format!("copr:copr.fedorainfracloud.org:{}:{}", owner, name));
+        } else {
+            return None;
+        }
+    }
+    (!ids.is_empty()).then_some(ids)
+}
+
+/// Returns the repository a config-manager line enables, in the dnf4 (`--set-enabled crb`),
+/// dnf5 (`setopt crb.enabled=1`) or yum-config-manager (`--enable crb`) syntax.
+pub fn config_manager_repo(line: &str) -> Option<&str> {
+    let (_, args) = line.split_once("config-manager ")?;
+    match args.trim().split_once(' ')? {
+        ("--set-enabled" | "--enable", repo) => Some(repo.trim()),
+        ("setopt", opt) => opt.trim().strip_suffix(".enabled=1"),
+        _ => None,
+    }
+}
+
+/// Runs `dnf group info` for the given group and returns its member listing. Metadata is
+/// fetched through `proxy` when one is configured.
+pub fn group_info(group: &str, proxy: Option<&str>) -> Result<Vec<GroupSection>, String> {
+    let output = Command::new("dnf").args(["-q", "group", "info", group])
+        .args(proxy.map(|url| format!("--setopt=proxy={}", url)))
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => "dnf is not available on this system".to_string(),
+            _ => format!("Failed to run dnf: {}", err),
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("dnf group info failed (offline or unknown group?):\n{}", stderr.trim()));
+    }
+
+    let sections = parse_group_info(&String::from_utf8_lossy(&output.stdout));
+    if sections.is_empty() {
+        return Err(format!("No members listed for group '{}'", group));
+    }
+    Ok(sections)
+}
+
+/// Parses `dnf group info` output. Handles both the dnf4 layout (indented "Title:" followed by
+/// indented members) and the dnf5 layout ("Title : member" with continuation lines).
+fn parse_group_info(text: &str) -> Vec<GroupSection> {
+    let mut sections: Vec<GroupSection> = Vec::new();
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some((title, member)) = trimmed.split_once(" : ").or_else(|| trimmed.strip_prefix(": ").map(|m| ("", m))) {
+            let title = title.trim();
+            if !title.is_empty() {
+                sections.push(GroupSection { title: title.to_string(), members: Vec::new() });
+            }
+            if let Some(section) = sections.last_mut() {
+                section.members.push(member.trim().to_string());
+            }
+            continue;
+        }
+        let is_member = line.starts_with("   ") || line.starts_with('\t');
+        match (trimmed.strip_suffix(':'), sections.last_mut()) {
+            (Some(title), _) if !is_member => sections.push(GroupSection { title: title.to_string(), members: Vec::new() }),
+            (None, Some(section)) if is_member => section.members.push(trimmed.to_string()),
+            _ => {}
+        }
+    }
+    // Keep only member listings, not metadata such as the description or repositories.
+    sections.retain(|section| {
+        let title = section.title.to_lowercase();
+        !section.members.is_empty() && (title.contains("package") || title.contains("group"))
+    });
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(title: &str, members: &[&str]) -> GroupSection {
+        GroupSection { title: title.to_string(), members: members.iter().map(|member| member.to_string()).collect() }
+    }
+
+    #[test]
+    fn group_members_keep_the_package_manager() {
+        let members = ["gnome-shell".to_string(), "@Fonts".to_string()];
+        assert_eq!(with_group_members("sudo yum groupinstall -y 'Workstation'", &members), "sudo yum install -y 'gnome-shell' '@Fonts'");
+        assert_eq!(with_group_members("dnf group install -y 'Workstation'\necho done", &members), "dnf install -y 'gnome-shell' '@Fonts'\necho done");
+    }
+
+    #[test]
+    fn reads_repos_from_every_config_manager_syntax() {
+        assert_eq!(config_manager_repo("sudo dnf config-manager --set-enabled crb"), Some("crb"));
+        assert_eq!(config_manager_repo("sudo dnf config-manager setopt crb.enabled=1"), Some("crb"));
+        assert_eq!(config_manager_repo("sudo yum-config-manager --enable crb"), Some("crb"));
+        assert_eq!(enabled_by("sudo yum copr enable -y atim/alacritty"), Some(vec!["copr:copr.fedorainfracloud.org:atim:alacritty".to_string()]));
+    }
+
+    #[test]
+    fn parses_dnf4_group_info() {
+        let output = "Group: Container Management
+ Description: Tools for managing Linux containers
+ Mandatory Packages:
+   buildah
+   podman
+ Default Packages:
+   flatpak-builder
+ Optional Packages:
+   toolbox
+";
+        assert_eq!(parse_group_info(output), vec![
+            section("Mandatory Packages", &["buildah", "podman"]),
+            section("Default Packages", &["flatpak-builder"]),
+            section("Optional Packages", &["toolbox"]),
+        ]);
+    }
+
+    #[test]
+    fn parses_dnf5_group_info() {
+        let output = "Id                   : container-management
+Name                 : Container Management
+Description          : Tools for managing Linux containers
+Installed            : no
+Order                : 
+Langonly             : 
+Uservisible          : yes
+Repositories         : appstream
+Mandatory packages   : buildah
+                     : podman
+Default packages     : flatpak-builder
+Optional packages    : toolbox
+";
+        assert_eq!(parse_group_info(output), vec![
+            section("Mandatory packages", &["buildah", "podman"]),
+            section("Default packages", &["flatpak-builder"]),
+            section("Optional packages", &["toolbox"]),
+        ]);
+    }
+
+    #[test]
+    fn empty_and_unknown_groups_have_no_sections() {
+        // A group without members still prints its name and description.
+        assert_eq!(parse_group_info("Group: Legacy UNIX Compatibility\n Description: Compatibility programs\n"), vec![]);
+        // dnf4 only warns about an unknown group, and on stderr; dnf5 fails without output.
+        assert_eq!(parse_group_info(""), vec![]);
+        assert_eq!(parse_group_info("Warning: Group no-such-group does not exist.\n"), vec![]);
+    }
+}
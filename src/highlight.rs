@@ -0,0 +1,159 @@
+// src/highlight.rs
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+};
+
+/// Commands and shell keywords worth picking out when skimming a generated script.
+const KEYWORDS: &[&str] = &[
+    "sudo", "dnf", "rpm", "flatpak", "systemctl", "systemd-run", "usermod", "tee", "sed", "cp",
+    "if", "then", "else", "fi", "for", "in", "do", "done", "set", "echo", "print_step",
+];
+
+fn comment() -> Style { Style::default().fg(Color::DarkGray) }
+fn keyword() -> Style { Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD) }
+fn string() -> Style { Style::default().fg(Color::Green) }
+fn variable() -> Style { Style::default().fg(Color::Yellow) }
+fn section() -> Style { Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD) }
+
+/// Width section rules are padded to, so phase boundaries line up in the preview.
+const SECTION_RULE_WIDTH: usize = 60;
+
+/// Styles a script for display: the `# --- TITLE ---` markers the generator emits between
+/// sections become full-width rules. With `syntax`, every other line is highlighted; this is a
+/// tokenizer, not a parser: it knows comments, quoted strings, `$VAR`/`${VAR}` expansions and a
+/// fixed list of keywords, which is enough for the scripts el-init generates.
+pub fn display(script: &str, syntax: bool) -> Text<'static> {
+    Text::from(script.lines().map(|line| match section_title(line) {
+        Some(title) => section_rule(title),
+        None if syntax => highlight_line(line),
+        None => Line::raw(line.to_string()),
+    }).collect::<Vec<_>>())
+}
+
+fn section_title(line: &str) -> Option<&str> {
+    line.trim().strip_prefix("# --- ")?.strip_suffix(" ---")
+}
+
+fn section_rule(title: &str) -> Line<'static> {
+    let fill = SECTION_RULE_WIDTH.saturating_sub(title.chars().count() + 4).max(3);
+    Line::styled(format!("── {} {}", title, "─".repeat(fill)), section())
+}
+
+fn highlight_line(line: &str) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let mut chars = line.char_indices().peekable();
+    let flush = |plain: &mut String, spans: &mut Vec<Span<'static>>| {
+        if !plain.is_empty() {
+            spans.push(Span::raw(std::mem::take(plain)));
+        }
+    };
+
+    while let Some((start, c)) = chars.next() {
+        match c {
+            // A comment starts a line or follows whitespace; `#` elsewhere is part of a word.
+            '#' if line[..start].chars().last().is_none_or(char::is_whitespace) => {
+                flush(&mut plain, &mut spans);
+                spans.push(Span::styled(line[start..].to_string(), comment()));
+                break;
+            }
+            '\'' | '"' => {
+                flush(&mut plain, &mut spans);
+                let mut end = line.len();
+                while let Some((index, next)) = chars.next() {
+                    if next == '\\' && c == '"' {
+                        chars.next();
+                    } else if next == c {
+                        end = index + 1;
+                        break;
+                    }
+                }
+                spans.push(Span::styled(line[start..end].to_string(), string()));
+            }
+            '$' => {
+                flush(&mut plain, &mut spans);
+                let mut end = start + 1;
+                if chars.peek().is_some_and(|(_, next)| *next == '{') {
+                    end = line[start..].find('}').map_or(line.len(), |close| start + close + 1);
+                } else {
+                    while let Some((index, next)) = chars.peek().copied() {
+                        if !(next.is_ascii_alphanumeric() || next == '_') {
+                            break;
+                        }
+                        end = index + 1;
+                        chars.next();
+                    }
+                }
+                while chars.peek().is_some_and(|(index, _)| *index < end) {
+                    chars.next();
+                }
+                spans.push(Span::styled(line[start..end].to_string(), variable()));
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '-' => {
+                let mut end = start + c.len_utf8();
+                while let Some((index, next)) = chars.peek().copied() {
+                    if !(next.is_alphanumeric() || matches!(next, '_' | '-' | '.' | '/' | '=' | ':' | '@' | '+')) {
+                        break;
+                    }
+                    end = index + next.len_utf8();
+                    chars.next();
+                }
+                let word = &line[start..end];
+                if KEYWORDS.contains(&word) {
+                    flush(&mut plain, &mut spans);
+                    spans.push(Span::styled(word.to_string(), keyword()));
+                } else {
+                    plain.push_str(word);
+                }
+            }
+            c => plain.push(c),
+        }
+    }
+    flush(&mut plain, &mut spans);
+    Line::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styled(line: &str) -> Vec<(String, Style)> {
+        highlight_line(line).spans.into_iter().map(|span| (span.content.into_owned(), span.style)).collect()
+    }
+
+    #[test]
+    fn tokens_are_styled() {
+        assert_eq!(styled(r#"sudo usermod -aG "$group" $TARGET_USER # done"#), vec![
+            ("sudo".to_string(), keyword()),
+            (" ".to_string(), Style::default()),
+            ("usermod".to_string(), keyword()),
+            (" -aG ".to_string(), Style::default()),
+            (r#""$group""#.to_string(), string()),
+            (" ".to_string(), Style::default()),
+            ("$TARGET_USER".to_string(), variable()),
+            (" ".to_string(), Style::default()),
+            ("# done".to_string(), comment()),
+        ]);
+    }
+
+    #[test]
+    fn section_markers_become_rules() {
+        let text = display("# --- 1. ENABLING REPOSITORIES ---\n# --- not a marker\nsudo dnf install -y tmux", false);
+        let lines: Vec<String> = text.lines.iter().map(|line| line.spans.iter().map(|span| span.content.as_ref()).collect()).collect();
+        assert!(lines[0].starts_with("── 1. ENABLING REPOSITORIES ──"));
+        assert_eq!(lines[0].chars().count(), SECTION_RULE_WIDTH);
+        assert_eq!(text.lines[0].spans[0].style, section());
+        assert_eq!(lines[1..], ["# --- not a marker", "sudo dnf install -y tmux"]);
+    }
+
+    #[test]
+    fn hash_inside_a_word_is_not_a_comment() {
+        assert_eq!(styled("echo a#b ${X}"), vec![
+            ("echo".to_string(), keyword()),
+            (" a#b ".to_string(), Style::default()),
+            ("${X}".to_string(), variable()),
+        ]);
+    }
+}
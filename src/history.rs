@@ -0,0 +1,40 @@
+// src/history.rs
+
+use std::{collections::HashMap, fs, path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
+
+/// When each item was last selected, persisted across sessions in the config directory.
+#[derive(Default)]
+pub struct History {
+    path: Option<PathBuf>,
+    last_used: HashMap<String, u64>,
+}
+
+impl History {
+    /// Loads the history file; a missing or unreadable file gives an empty history.
+    pub fn load() -> History {
+        let path = crate::config_dir().map(|dir| dir.join("history.json"));
+        let last_used = path.as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        History { path, last_used }
+    }
+
+    /// Records that `id` was selected now and saves the history. Save errors are ignored;
+    /// the history only affects ordering.
+    pub fn record(&mut self, id: &str) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+        self.last_used.insert(id.to_string(), now);
+        if let Some(path) = &self.path
+            && let Ok(text) = serde_json::to_string(&self.last_used)
+        {
+            let _ = path.parent().map(fs::create_dir_all);
+            let _ = fs::write(path, text);
+        }
+    }
+
+    /// Seconds since the epoch when `id` was last selected.
+    pub fn last_used(&self, id: &str) -> Option<u64> {
+        self.last_used.get(id).copied()
+    }
+}
@@ -0,0 +1,40 @@
+// src/input.rs
+
+use crossterm::event::KeyCode;
+use ratatui::{
+    style::{Color, Style},
+    widgets::{Block, Borders, Paragraph},
+};
+
+/// A single-line text field used by popups and forms.
+#[derive(Debug, Default, Clone)]
+pub struct TextInput {
+    pub value: String,
+}
+
+impl TextInput {
+    pub fn new(value: impl Into<String>) -> TextInput {
+        TextInput { value: value.into() }
+    }
+
+    /// Applies an editing key. Returns `true` if the key was consumed.
+    pub fn handle_key(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Char(c) => self.value.push(c),
+            KeyCode::Backspace => { self.value.pop(); },
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.value.clear();
+    }
+
+    /// Builds the bordered widget, highlighting the border when focused.
+    pub fn widget<'a>(&'a self, title: &'a str, focused: bool) -> Paragraph<'a> {
+        let border_style = if focused { Style::default().fg(Color::Yellow) } else { Style::default() };
+        let text = if focused { format!("{}_", self.value) } else { self.value.clone() };
+        Paragraph::new(text).block(Block::default().title(title).borders(Borders::ALL).border_style(border_style))
+    }
+}
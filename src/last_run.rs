@@ -0,0 +1,71 @@
+// src/last_run.rs
+
+use serde::Deserialize;
+use std::{env, fs, path::PathBuf};
+
+/// Where scripts generated with step recording write their run log, relative to the home
+/// directory of the user who invoked them.
+pub const LOG_PATH: &str = ".local/share/el-init/last-run.json";
+
+/// One step of the last run, as the generated script's `record_step` helper writes it.
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct Step {
+    pub step: String,
+    pub exit_code: i32,
+    pub seconds: u64,
+}
+
+/// Reads the log of the last recorded run from the home directory; `None` without one.
+pub fn load() -> Option<Vec<Step>> {
+    let home = env::var_os("HOME").filter(|home| !home.is_empty())?;
+    let text = fs::read_to_string(PathBuf::from(home).join(LOG_PATH)).ok()?;
+    Some(parse(&text))
+}
+
+/// Parses the JSON lines of a run log. Lines that do not parse, such as one cut short when the
+/// machine went down, are skipped.
+pub fn parse(text: &str) -> Vec<Step> {
+    text.lines().filter_map(|line| serde_json::from_str(line).ok()).collect()
+}
+
+/// A breakdown of the run: one line per step with its duration and a mark for failures,
+/// then the total time and the outcome.
+pub fn report(steps: &[Step]) -> String {
+    if steps.is_empty() {
+        return "The last run recorded no steps.".to_string();
+    }
+    let width = steps.iter().map(|step| step.step.chars().count()).max().unwrap_or(0);
+    let mut text = String::new();
+    for step in steps {
+        let status = if step.exit_code == 0 { String::new() } else { format!("  FAILED (exit code {})", step.exit_code) };
+        text.push_str(&format!("{:<width$}  {:>8}{}\n", step.step, duration(step.seconds), status, width = width));
+    }
+    let total: u64 = steps.iter().map(|step| step.seconds).sum();
+    let outcome = match steps.iter().find(|step| step.exit_code != 0) {
+        Some(failed) => format!("failed at \"{}\"", failed.step),
+        None => "succeeded".to_string(),
+    };
+    text.push_str(&format!("\nTotal {}, {}.", duration(total), outcome));
+    text
+}
+
+fn duration(seconds: u64) -> String {
+    if seconds < 60 { format!("{}s", seconds) } else { format!("{}m {:02}s", seconds / 60, seconds % 60) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_json_lines_and_skips_broken_ones() {
+        let steps = parse("{\"step\": \"EPEL\", \"exit_code\": 0, \"seconds\": 75}\n\
+            {\"step\": \"Cockpit \\\"full\\\"\", \"exit_code\": 1, \"seconds\": 3}\n{\"step\": \"cut");
+        assert_eq!(steps, vec![
+            Step { step: "EPEL".to_string(), exit_code: 0, seconds: 75 },
+            Step { step: "Cockpit \"full\"".to_string(), exit_code: 1, seconds: 3 },
+        ]);
+        assert_eq!(report(&steps), "EPEL              1m 15s\n\
+            Cockpit \"full\"        3s  FAILED (exit code 1)\n\nTotal 1m 18s, failed at \"Cockpit \"full\"\".");
+    }
+}
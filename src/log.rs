@@ -0,0 +1,31 @@
+// src/log.rs
+
+use std::{fmt, fs::File, io::Write, path::Path, time::Instant};
+
+/// Appends timestamped lines describing the session to a file given with `--log-file`.
+pub struct SessionLog {
+    file: File,
+    started: Instant,
+}
+
+impl SessionLog {
+    pub fn create(path: &Path) -> std::io::Result<SessionLog> {
+        Ok(SessionLog { file: File::create(path)?, started: Instant::now() })
+    }
+
+    /// Writes one line. Write errors are ignored so logging never disturbs the UI.
+    pub fn write(&mut self, args: fmt::Arguments) {
+        let elapsed = self.started.elapsed();
+        let _ = writeln!(self.file, "[{:>5}.{:03}] {}", elapsed.as_secs(), elapsed.subsec_millis(), args);
+    }
+}
+
+/// Logs to `$app.log` if logging is enabled. The message is not formatted otherwise.
+macro_rules! log_event {
+    ($app:expr, $($arg:tt)*) => {
+        if let Some(log) = $app.log.as_mut() {
+            log.write(format_args!($($arg)*));
+        }
+    };
+}
+pub(crate) use log_event;
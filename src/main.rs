@@ -1,9 +1,25 @@
 // src/main.rs
 
+mod cli;
+mod clipboard;
+mod deps;
+mod dnf;
+mod highlight;
+mod history;
+mod input;
+mod last_run;
+mod log;
+mod members;
+mod os_release;
+mod pins;
+mod profile;
+mod recovery;
+mod runner;
 mod scripts;
+mod settings;
 
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -11,59 +27,316 @@ use ratatui::{
     backend::{Backend, CrosstermBackend},
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Wrap},
-    Frame, Terminal,
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Tabs, Wrap},
+    Frame, Terminal, TerminalOptions, Viewport,
 };
-use std::{cell::RefCell, error::Error, io, fs, process::Command, os::unix::fs::PermissionsExt, rc::Rc};
+use history::History;
+use input::TextInput;
+use log::{log_event, SessionLog};
+use dnf::GroupSection;
+use members::MemberPicker;
+use os_release::OsRelease;
+use runner::Privilege;
+use scripts::{Arch, DnfFlavor, PackageManager, ScriptContext, ScriptFn};
+use pins::{PinForm, VersionPin};
+use profile::{Profile, ProfileAction, ProfileManager, TomlProfile};
+use settings::SettingsForm;
+use std::{cell::RefCell, collections::{BTreeMap, BTreeSet, HashMap, HashSet}, env, error::Error, io::{self, IsTerminal, Read}, fs, path::{Path, PathBuf}, rc::Rc, sync::mpsc, thread, time::{Duration, Instant}};
 
 // A category for each script to control execution order.
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum ScriptCategory {
     Repository,
+    Copr,
     General,
 }
 
+impl ScriptCategory {
+    /// Whether an item of this category is shown under `filter`. COPRs count as repositories.
+    fn matches_filter(self, filter: ScriptCategory) -> bool {
+        match filter {
+            ScriptCategory::Repository | ScriptCategory::Copr => self != ScriptCategory::General,
+            ScriptCategory::General => self == ScriptCategory::General,
+        }
+    }
+}
+
+/// Rough size of what an item installs, shown next to it in the menu.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum InstallWeight {
+    Light,
+    Medium,
+    Heavy,
+}
+
+impl InstallWeight {
+    fn badge(&self, ascii: bool) -> &'static str {
+        match (self, ascii) {
+            (InstallWeight::Light, false) => "●",
+            (InstallWeight::Medium, false) => "●●",
+            (InstallWeight::Heavy, false) => "●●●",
+            (InstallWeight::Light, true) => "[light]",
+            (InstallWeight::Medium, true) => "[medium]",
+            (InstallWeight::Heavy, true) => "[heavy]",
+        }
+    }
+}
+
+/// Optional, informational metadata attached to menu items.
+#[derive(Debug, Default, Clone)]
+pub struct ItemMeta {
+    pub weight: Option<InstallWeight>,
+    /// The change only takes full effect after a reboot (new kernel, boot target, ...).
+    pub needs_reboot: bool,
+    /// Ids of items that must be installed first; they are added automatically.
+    pub requires: &'static [&'static str],
+    /// Only usable from a Wayland session (Sway and its companions).
+    pub wayland_only: bool,
+    /// Cross-cutting labels such as "gui", "server" or "experimental", for the tag filter.
+    pub tags: &'static [&'static str],
+    /// System config files (globs allowed) the step modifies; the script backs them up first.
+    pub touches_config: &'static [&'static str],
+    /// The script is only a placeholder `echo`; selecting the item installs nothing yet.
+    pub is_stub: bool,
+}
+
+/// Whether an item can be selected on the target distro.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Availability {
+    Available,
+    /// Shown greyed out with the reason, e.g. "not available on RHEL"; cannot be toggled.
+    Unavailable(&'static str),
+}
+
 // A struct to hold all info about a selected item.
+#[derive(Clone)]
 struct SelectedItem {
+    id: &'static str,
     name: String,
-    script_fn: fn() -> &'static str,
+    // Menu path plus name, e.g. "Virtualization > KVM Management > Cockpit > Base Installation".
+    qualified_name: String,
+    script_fn: ScriptFn,
     category: ScriptCategory,
+    needs_reboot: bool,
+    requires: &'static [&'static str],
+    wayland_only: bool,
+    touches_config: &'static [&'static str],
+    is_stub: bool,
+    // Not selected by the user, but required by an item that is.
+    auto_added: bool,
+    note: Option<String>,
 }
 
-/// Represents a node in the menu tree. It can be a selectable item or a sub-menu.
+/// Represents a node in the menu tree. It can be a selectable item, a sub-menu, or a
+/// radio group whose items are mutually exclusive.
 pub enum MenuNode {
     Item {
+        /// Stable identifier that survives renames and menu rebuilds.
+        id: &'static str,
         name: String,
-        script_fn: fn() -> &'static str,
+        script_fn: ScriptFn,
         selected: bool,
         category: ScriptCategory,
+        meta: ItemMeta,
+        /// Free-text note from the user, emitted as a comment above the item's commands.
+        note: Option<String>,
+        availability: Availability,
     },
     Menu {
         name: String,
         children: Vec<Rc<RefCell<MenuNode>>>,
     },
+    Radio {
+        name: String,
+        children: Vec<Rc<RefCell<MenuNode>>>,
+    },
 }
 
 impl MenuNode {
+    fn name(&self) -> &str {
+        match self {
+            MenuNode::Item { name, .. } | MenuNode::Menu { name, .. } | MenuNode::Radio { name, .. } => name,
+        }
+    }
+
+    /// The children of a menu or radio group; `None` for items.
+    fn children(&self) -> Option<&Vec<Rc<RefCell<MenuNode>>>> {
+        match self {
+            MenuNode::Menu { children, .. } | MenuNode::Radio { children, .. } => Some(children),
+            MenuNode::Item { .. } => None,
+        }
+    }
+
     /// Recursively collects detailed info about all selected items.
     fn get_selected_items_info(&self, items: &mut Vec<SelectedItem>) {
+        self.collect_items(None, items, false);
+    }
+
+    /// Collection helper; `parent_path` is `None` at the root, whose name is not part of paths.
+    /// With `all`, unselected items are collected too.
+    fn collect_items(&self, parent_path: Option<&str>, items: &mut Vec<SelectedItem>, all: bool) {
         match self {
-            MenuNode::Item { name, selected, script_fn, category, .. } => {
-                if *selected {
+            MenuNode::Item { id, name, selected, script_fn, category, meta, note, .. } => {
+                if *selected || all {
+                    let qualified_name = match parent_path {
+                        Some(path) if !path.is_empty() => format!("{} > {}", path, name),
+                        _ => name.clone(),
+                    };
                     items.push(SelectedItem {
+                        id,
                         name: name.clone(),
+                        qualified_name,
                         script_fn: *script_fn,
                         category: *category,
+                        needs_reboot: meta.needs_reboot,
+                        requires: meta.requires,
+                        wayland_only: meta.wayland_only,
+                        touches_config: meta.touches_config,
+                        is_stub: meta.is_stub,
+                        auto_added: false,
+                        note: note.clone(),
                     });
                 }
             }
-            MenuNode::Menu { children, .. } => {
+            MenuNode::Menu { name, children } | MenuNode::Radio { name, children } => {
+                let path = match parent_path {
+                    None => String::new(),
+                    Some("") => name.clone(),
+                    Some(path) => format!("{} > {}", path, name),
+                };
+                for child in children {
+                    child.borrow().collect_items(Some(&path), items, all);
+                }
+            }
+        }
+    }
+
+    /// Recursively collects the ids of selected items.
+    fn selected_ids(&self, ids: &mut HashSet<&'static str>) {
+        match self {
+            MenuNode::Item { id, selected, .. } => {
+                if *selected {
+                    ids.insert(id);
+                }
+            }
+            MenuNode::Menu { children, .. } | MenuNode::Radio { children, .. } => {
+                for child in children {
+                    child.borrow().selected_ids(ids);
+                }
+            }
+        }
+    }
+
+    /// Recursively collects the tags used by any item.
+    fn tags(&self, tags: &mut BTreeSet<&'static str>) {
+        match self {
+            MenuNode::Item { meta, .. } => tags.extend(meta.tags),
+            MenuNode::Menu { children, .. } | MenuNode::Radio { children, .. } => {
+                for child in children {
+                    child.borrow().tags(tags);
+                }
+            }
+        }
+    }
+
+    /// Recursively collects the notes of all items, by id.
+    fn notes(&self, notes: &mut HashMap<&'static str, String>) {
+        match self {
+            MenuNode::Item { id, note: Some(note), .. } => {
+                notes.insert(id, note.clone());
+            }
+            MenuNode::Item { .. } => {}
+            MenuNode::Menu { children, .. } | MenuNode::Radio { children, .. } => {
+                for child in children {
+                    child.borrow().notes(notes);
+                }
+            }
+        }
+    }
+
+    /// Recursively sets the notes of the items whose id is in `notes`.
+    fn restore_notes(&mut self, notes: &HashMap<&'static str, String>) {
+        match self {
+            MenuNode::Item { id, note, .. } => {
+                if let Some(saved) = notes.get(id) {
+                    *note = Some(saved.clone());
+                }
+            }
+            MenuNode::Menu { children, .. } | MenuNode::Radio { children, .. } => {
                 for child in children {
-                    child.borrow().get_selected_items_info(items);
+                    child.borrow_mut().restore_notes(notes);
                 }
             }
         }
     }
+
+    /// Recursively selects exactly the available items whose id is in `ids`.
+    fn select_ids(&mut self, ids: &HashSet<&'static str>) {
+        match self {
+            MenuNode::Item { id, selected, availability, .. } => {
+                *selected = ids.contains(id) && *availability == Availability::Available;
+            }
+            MenuNode::Menu { children, .. } | MenuNode::Radio { children, .. } => {
+                for child in children {
+                    child.borrow_mut().select_ids(ids);
+                }
+            }
+        }
+    }
+
+    /// Recursively counts the selected items at or below this node.
+    fn count_selected(&self) -> usize {
+        match self {
+            MenuNode::Item { selected, .. } => usize::from(*selected),
+            MenuNode::Menu { children, .. } | MenuNode::Radio { children, .. } => {
+                children.iter().map(|child| child.borrow().count_selected()).sum()
+            }
+        }
+    }
+}
+
+/// The available items at or below `node`, leaving out radio groups, since inverting them
+/// would select several options.
+fn invertible_items(node: &Rc<RefCell<MenuNode>>, items: &mut Vec<Rc<RefCell<MenuNode>>>) {
+    match &*node.borrow() {
+        MenuNode::Item { availability: Availability::Available, .. } => items.push(node.clone()),
+        MenuNode::Item { .. } | MenuNode::Radio { .. } => {}
+        MenuNode::Menu { children, .. } => {
+            for child in children {
+                invertible_items(child, items);
+            }
+        }
+    }
+}
+
+/// Finds the menu or radio group that directly contains `target`.
+fn find_parent(node: &Rc<RefCell<MenuNode>>, target: &Rc<RefCell<MenuNode>>) -> Option<Rc<RefCell<MenuNode>>> {
+    let node_borrow = node.borrow();
+    let children = node_borrow.children()?;
+    if children.iter().any(|child| Rc::ptr_eq(child, target)) {
+        return Some(node.clone());
+    }
+    children.iter().find_map(|child| find_parent(child, target))
+}
+
+/// Formats the install-weight badge shown after an item's name.
+fn weight_suffix(meta: &ItemMeta, glyphs: &TreeGlyphs) -> String {
+    match meta.weight {
+        Some(weight) => format!(" {}", weight.badge(glyphs.ascii)),
+        None => String::new(),
+    }
+}
+
+/// Formats a menu row, appending a badge when the menu has selected descendants.
+fn menu_label(name: &str, node: &MenuNode) -> String {
+    if node.children().is_some_and(|children| children.is_empty()) {
+        return format!("{} (empty)", name);
+    }
+    match node.count_selected() {
+        0 => format!("{} >", name),
+        count => format!("{} > ({})", name, count),
+    }
 }
 
 
@@ -72,22 +345,167 @@ impl MenuNode {
 pub enum OsDistribution {
     Rhel,
     Centos,
+    CentosStream,
     Rocky,
     AlmaLinux,
+    /// Another EL rebuild (Oracle Linux, EuroLinux, ...) recognized through `ID_LIKE`.
+    /// It gets the generic scripts, which enable repositories with config-manager as on the
+    /// other rebuilds; the RHEL ones would need subscription-manager.
+    GenericEl,
     Unknown,
 }
 
+/// Characters used to draw the menu tree.
+struct TreeGlyphs {
+    ascii: bool,
+    branch: &'static str,
+    last: &'static str,
+    pipe: &'static str,
+}
+
+const UNICODE_GLYPHS: TreeGlyphs = TreeGlyphs { ascii: false, branch: "├─", last: "└─", pipe: "│  " };
+const ASCII_GLYPHS: TreeGlyphs = TreeGlyphs { ascii: true, branch: "|-", last: "`-", pipe: "|  " };
+
+impl OsDistribution {
+    /// Distros that can be chosen as the target, in cycling order.
+    const TARGETS: [OsDistribution; 5] = [
+        OsDistribution::Rhel,
+        OsDistribution::Centos,
+        OsDistribution::CentosStream,
+        OsDistribution::Rocky,
+        OsDistribution::AlmaLinux,
+    ];
+
+    /// The next target distro after this one, wrapping around.
+    fn next_target(self) -> OsDistribution {
+        let index = OsDistribution::TARGETS.iter().position(|os| *os == self);
+        OsDistribution::TARGETS[index.map_or(0, |i| (i + 1) % OsDistribution::TARGETS.len())]
+    }
+
+    /// Machine-readable identifier, matching `ID` in os-release where one exists.
+    fn id(&self) -> &'static str {
+        match self {
+            OsDistribution::Rhel => "rhel",
+            OsDistribution::Centos => "centos",
+            OsDistribution::CentosStream => "centos-stream",
+            OsDistribution::Rocky => "rocky",
+            OsDistribution::AlmaLinux => "almalinux",
+            OsDistribution::GenericEl => "el",
+            OsDistribution::Unknown => "unknown",
+        }
+    }
+}
+
 /// Enum to manage the overall state of the application.
+#[derive(Debug, Clone, PartialEq)]
 enum AppState {
     Running,
     Finished,
     Saving,
+    Settings,
+    Pinning,
+    Excluding,
+    Noting,
+    Profiles,
+    Tagging,
+    Expanding,
+    Scheduling,
+    Timer,
+    Report,
+    Searching,
+    Confirm(Confirmation),
+    /// A recoverable error shown as a dismissible modal over the previous screen.
+    Error(String),
+}
+
+/// What the review screen generates and saves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Bash,
+    /// A Containerfile for building an image of the target distro with the selected components.
+    Containerfile,
+    /// A cloud-init `#cloud-config` user-data file that runs each component from `runcmd`.
+    CloudInit,
+    /// One bash script for every target distro, branching on os-release where they differ.
+    Bundle,
+}
+
+impl OutputFormat {
+    fn next(self) -> OutputFormat {
+        match self {
+            OutputFormat::Bash => OutputFormat::Containerfile,
+            OutputFormat::Containerfile => OutputFormat::CloudInit,
+            OutputFormat::CloudInit => OutputFormat::Bundle,
+            OutputFormat::Bundle => OutputFormat::Bash,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            OutputFormat::Bash => "bash",
+            OutputFormat::Containerfile => "Containerfile",
+            OutputFormat::CloudInit => "cloud-init",
+            OutputFormat::Bundle => "multi-distro bash",
+        }
+    }
+
+    /// Whether the output is a bash script that can be checked and run.
+    fn is_bash(self) -> bool {
+        matches!(self, OutputFormat::Bash | OutputFormat::Bundle)
+    }
+}
+
+/// Actions that ask the user for confirmation before they happen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Confirmation {
+    Quit,
+    RunWithReboot,
+    /// Restore the selection autosaved by a run that did not exit cleanly.
+    RestoreSession,
+}
+
+impl Confirmation {
+    fn prompt(&self) -> &'static str {
+        match self {
+            Confirmation::Quit => "You have selected components. Quit without running or saving?",
+            Confirmation::RunWithReboot => "The system will reboot after the script finishes. Run it now?",
+            Confirmation::RestoreSession => "el-init did not exit cleanly last time. Restore your previous session?",
+        }
+    }
 }
 
 /// Enum to tell the main function what to do after the TUI exits.
 pub enum ActionAfterExit {
-    Quit,
-    RunScript(String),
+    Quit(ExitSummary),
+    /// Run the script. `saved_path` is set when the user saved it and it should be run from there.
+    /// With `two_phase`, the repository section runs first and installs wait for confirmation.
+    RunScript { script: String, saved_path: Option<PathBuf>, two_phase: bool },
+    /// Install the script with a oneshot systemd unit that runs it on the next boot.
+    InstallFirstboot { script: String },
+    /// Install the script with a systemd timer that runs it on `schedule` (an `OnCalendar=` value).
+    InstallTimer { script: String, schedule: String },
+}
+
+/// What is printed after the TUI closes without running anything.
+pub struct ExitSummary {
+    detected: OsDistribution,
+    selected: usize,
+    /// Scripts saved during the session, oldest first.
+    saved: Vec<PathBuf>,
+}
+
+impl std::fmt::Display for ExitSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let plural = if self.selected == 1 { "" } else { "s" };
+        writeln!(f, "el-init: detected {:?}, {} item{} selected.", self.detected, self.selected, plural)?;
+        if self.saved.is_empty() {
+            return writeln!(f, "No script was saved.");
+        }
+        for path in &self.saved {
+            writeln!(f, "Saved script: {}", path.display())?;
+        }
+        Ok(())
+    }
 }
 
 /// Holds the application's state.
@@ -96,36 +514,331 @@ struct App {
     menu_tree: Rc<RefCell<MenuNode>>,
     nav_path: Vec<Rc<RefCell<MenuNode>>>,
     selected_index: usize,
+    // The distro scripts are generated for; starts as `detected_os` and can be switched.
     os_distro: OsDistribution,
+    detected_os: OsDistribution,
+    os_version: Option<u32>,
+    // What this host installs with; scripts for any other target use dnf.
+    host_package_manager: PackageManager,
+    /// CPU architecture the scripts are generated for.
+    arch: Arch,
+    output_format: OutputFormat,
     reboot_requested: bool,
-    filename_input: String,
-    save_status_message: Option<String>,
+    filename_input: TextInput,
+    // Result of the last save and when it was shown; it disappears after STATUS_MESSAGE_DURATION.
+    save_status_message: Option<(String, Instant)>,
+    no_sudo: bool,
+    assume_yes: bool,
+    previous_state: AppState,
+    show_help: bool,
+    show_preview: bool,
+    wrap_navigation: bool,
+    script_ctx: ScriptContext,
+    settings_form: Option<SettingsForm>,
+    info_popup: Option<(String, String)>,
+    // Repository detail text shown by [d], and how far it is scrolled.
+    detail_popup: Option<(String, &'static str)>,
+    detail_scroll: u16,
+    // Titled groups of the [W] "what would change" report, and its scroll offset.
+    change_report: Vec<(String, Vec<String>)>,
+    report_scroll: u16,
+    // Steps of the last run recorded by a script with run recording on, shown by [L].
+    last_run: Option<Vec<last_run::Step>>,
+    ascii: bool,
+    // Colors bash syntax in the script previews; off by default when NO_COLOR is set.
+    syntax_highlight: bool,
+    /// Extra flags for every generated dnf install, for dependency conflicts with extra repos.
+    allow_erasing: bool,
+    nobest: bool,
+    // Cursor position in each ancestor of the current menu, parallel to `nav_path`.
+    index_stack: Vec<usize>,
+    // Menus left via Back, with their cursor, for browser-style forward navigation.
+    forward_stack: Vec<(Rc<RefCell<MenuNode>>, usize)>,
+    // Highlighted row in the finished screen's selected-items list.
+    finished_index: usize,
+    show_full_script: bool,
+    // Set while the save popup was opened by "save and run".
+    save_and_run: bool,
+    // Saves one script per category (`<base>-repos.sh`, `<base>-packages.sh`) instead of one.
+    split_output: bool,
+    // The script runs over SSH (this session or --deploy), so a reboot would cut the connection.
+    over_ssh: bool,
+    /// Whether this user is root, can use sudo, or has no sudo; root gets scripts without sudo.
+    privilege: Privilege,
+    run_saved_path: Option<PathBuf>,
+    // Every script written by a save in this session, for the exit summary.
+    saved_paths: Vec<PathBuf>,
+    // The `--profile` TOML file, read on startup and written by [p] on the review screen.
+    profile_path: Option<PathBuf>,
+    two_phase: bool,
+    // Index of the top-level menu shown as the active tab; `nav_path` is rooted at it.
+    active_tab: usize,
+    // When set, the menu shows a flat list of matching items from the whole tree.
+    category_filter: Option<ScriptCategory>,
+    /// When non-empty, only items carrying at least one of these tags are shown.
+    tag_filter: BTreeSet<&'static str>,
+    // Highlighted row of the tag picker.
+    tag_index: usize,
+    // Shows every item as one alphabetical list instead of the tree.
+    flat_view: bool,
+    // Debug mode (--debug) and whether item ids are currently shown in the menu.
+    debug: bool,
+    show_ids: bool,
+    // Orders each menu level by how recently its items were selected.
+    recent_first: bool,
+    history: History,
+    // Repositories already enabled on this machine, probed once at startup.
+    enabled_repos: HashSet<String>,
+    // Item whose note is being edited, and the note text.
+    note_target: Option<Rc<RefCell<MenuNode>>>,
+    note_input: TextInput,
+    /// Wall-clock time ("HH:MM") to reboot at instead of right after the script, and its input.
+    reboot_at: Option<String>,
+    reboot_at_input: TextInput,
+    // `OnCalendar=` expression typed for the [S] timer.
+    schedule_input: TextInput,
+    // The [/] search query; while it is non-empty the menu shows only the items matching it.
+    search_input: TextInput,
+    // Version pins per item id, and the popup editing one of them.
+    version_pins: HashMap<&'static str, VersionPin>,
+    /// Items never added automatically as prerequisites; the user manages them outside the tool.
+    no_auto_include: HashSet<&'static str>,
+    pin_form: Option<PinForm>,
+    // Package globs excluded from each group-install item, and the item being edited.
+    group_excludes: HashMap<&'static str, Vec<String>>,
+    /// Members chosen for group-install items that were expanded; replaces the group install.
+    group_members: HashMap<&'static str, Vec<String>>,
+    member_picker: Option<MemberPicker>,
+    // A `dnf group info` lookup still running for [g] or [G].
+    group_lookup: Option<GroupLookup>,
+    /// Whether selection changes are autosaved for crash recovery; only the interactive TUI sets it.
+    crash_recovery: bool,
+    // When the selection last changed without being autosaved, and the session offered for restore.
+    recovery_due: Option<Instant>,
+    recovered: Option<Profile>,
+    exclude_target: Option<&'static str>,
+    exclude_input: TextInput,
+    profile_manager: Option<ProfileManager>,
+    // Last generated script and the reboot flag it was generated with.
+    cached_script: Option<String>,
+    cached_reboot: bool,
+    // Set when a change affects the script, so the next `script` call regenerates it.
+    dirty: bool,
+    // Script text edited in $EDITOR and the reboot flag it was generated with. It replaces the
+    // generated bash script until the selection or options change.
+    edited_script: Option<(bool, String)>,
+    log: Option<SessionLog>,
+}
+
+/// Every item below `node` with its menu-qualified name, as `SelectedItem::qualified_name`
+/// spells it; `parent_path` is `None` at the root.
+fn item_nodes(node: &Rc<RefCell<MenuNode>>, parent_path: Option<&str>, items: &mut Vec<(String, Rc<RefCell<MenuNode>>)>) {
+    match &*node.borrow() {
+        MenuNode::Item { name, .. } => {
+            let qualified_name = match parent_path {
+                Some(path) if !path.is_empty() => format!("{} > {}", path, name),
+                _ => name.clone(),
+            };
+            items.push((qualified_name, node.clone()));
+        }
+        MenuNode::Menu { name, children } | MenuNode::Radio { name, children } => {
+            let path = match parent_path {
+                None => String::new(),
+                Some("") => name.clone(),
+                Some(path) => format!("{} > {}", path, name),
+            };
+            for child in children {
+                item_nodes(child, Some(&path), items);
+            }
+        }
+    }
+}
+
+/// A `dnf group info` lookup running on a background thread.
+struct GroupLookup {
+    group: String,
+    /// The item whose member picker opens with the result; `None` shows the members instead.
+    expand: Option<&'static str>,
+    result: mpsc::Receiver<Result<Vec<GroupSection>, String>>,
 }
 
 fn detect_os() -> OsDistribution {
-    if let Ok(content) = fs::read_to_string("/etc/os-release") {
-        for line in content.lines() {
-            if line.starts_with("ID=") {
-                let id = line.trim_start_matches("ID=").trim_matches('"');
-                return match id {
-                    "rhel" => OsDistribution::Rhel,
-                    "centos" => OsDistribution::Centos,
-                    "rocky" => OsDistribution::Rocky,
-                    "almalinux" => OsDistribution::AlmaLinux,
-                    _ => OsDistribution::Unknown,
-                };
+    OsRelease::load().map_or(OsDistribution::Unknown, |release| release.distribution())
+}
+
+/// Reads the EL major version from os-release (`PLATFORM_ID`, else `VERSION_ID`).
+fn detect_os_version() -> Option<u32> {
+    OsRelease::load()?.major_version()
+}
+
+/// Prints the detected distro for wrapper scripts. Exits nonzero when it is not supported.
+fn check_distro(json: bool) -> ! {
+    let os = detect_os();
+    let version = detect_os_version();
+    if json {
+        let version = version.map_or("null".to_string(), |v| v.to_string());
+        println!("{{\"id\": \"{}\", \"version\": {}, \"supported\": {}}}", os.id(), version, os != OsDistribution::Unknown);
+    } else {
+        match version {
+            Some(version) => println!("{} {}", os.id(), version),
+            None => println!("{}", os.id()),
+        }
+    }
+    std::process::exit(if os == OsDistribution::Unknown { 1 } else { 0 });
+}
+
+/// Prints the items selected in only one of two profile files. Exits 0 when the selections
+/// match, 1 when they differ and 2 when a file cannot be read.
+fn diff_profiles(a: &Path, b: &Path) -> ! {
+    let read = |path: &Path| fs::read_to_string(path).map_err(|err| err.to_string())
+        .and_then(|text| Profile::from_json(&text))
+        .unwrap_or_else(|err| {
+            eprintln!("el-init: {}: {}", path.display(), err);
+            std::process::exit(2);
+        });
+    let (profile_a, profile_b) = (read(a), read(b));
+    let tree = scripts::build_menu_tree(detect_os(), Arch::host(), false);
+    let (text, differ) = profile_diff(&tree.borrow(), (&a.display().to_string(), &profile_a), (&b.display().to_string(), &profile_b));
+    print!("{}", text);
+    std::process::exit(if differ { 1 } else { 0 });
+}
+
+/// The items selected in only one of the two named profiles, grouped by category in menu
+/// order; ids no menu item has are listed last. Also returns whether there was any difference.
+fn profile_diff(tree: &MenuNode, (name_a, a): (&str, &Profile), (name_b, b): (&str, &Profile)) -> (String, bool) {
+    let mut all = Vec::new();
+    tree.collect_items(None, &mut all, true);
+    let category = |id: &str| match all.iter().find(|item| item.id == id).map(|item| item.category) {
+        Some(ScriptCategory::Repository) => 0,
+        Some(ScriptCategory::Copr) => 1,
+        Some(ScriptCategory::General) => 2,
+        None => 3,
+    };
+    let position = |id: &str| all.iter().position(|item| item.id == id).unwrap_or(usize::MAX);
+    const CATEGORIES: [&str; 4] = ["Repositories", "COPR", "Configuration", "Unknown ids"];
+
+    let mut text = String::new();
+    let mut differ = false;
+    for (name, ours, theirs) in [(name_a, a, b), (name_b, b, a)] {
+        let mut only: Vec<&str> = ours.items.iter().map(String::as_str).filter(|id| !theirs.items.iter().any(|other| other == id)).collect();
+        only.sort_by_key(|id| (category(id), position(id), id.to_string()));
+        only.dedup();
+        text.push_str(&format!("Only in {}:\n", name));
+        if only.is_empty() {
+            text.push_str("  (nothing)\n");
+        }
+        differ |= !only.is_empty();
+        for (index, title) in CATEGORIES.iter().enumerate() {
+            let ids: Vec<&str> = only.iter().copied().filter(|id| category(id) == index).collect();
+            if !ids.is_empty() {
+                text.push_str(&format!("  {}:\n", title));
+                for id in ids {
+                    text.push_str(&format!("    {}\n", id));
+                }
             }
         }
     }
-    OsDistribution::Unknown
+    (text, differ)
+}
+
+/// Validates a reboot time of day for `shutdown -r`, normalizing "3:00" to "03:00".
+fn parse_reboot_time(text: &str) -> Result<String, String> {
+    let invalid = || format!("Invalid reboot time '{}': use HH:MM in 24-hour time, e.g. 03:00.", text);
+    let (hours, minutes) = text.trim().split_once(':').ok_or_else(invalid)?;
+    let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if hours.is_empty() || hours.len() > 2 || minutes.len() != 2 || !digits(hours) || !digits(minutes) {
+        return Err(invalid());
+    }
+    match (hours.parse::<u8>(), minutes.parse::<u8>()) {
+        (Ok(hours), Ok(minutes)) if hours < 24 && minutes < 60 => Ok(format!("{:02}:{:02}", hours, minutes)),
+        _ => Err(invalid()),
+    }
+}
+
+/// The per-user config directory, `$XDG_CONFIG_HOME/el-init` or `~/.config/el-init`.
+fn config_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()).map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").filter(|home| !home.is_empty()).map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("el-init"))
+}
+
+/// Most recent selection time of an item, or of any item below a menu.
+fn recency(node: &MenuNode, history: &History) -> Option<u64> {
+    match node {
+        MenuNode::Item { id, .. } => history.last_used(id),
+        MenuNode::Menu { children, .. } | MenuNode::Radio { children, .. } => {
+            children.iter().filter_map(|child| recency(&child.borrow(), history)).max()
+        }
+    }
+}
+
+/// The children in display order: as built (alphabetical), or most recently used first.
+fn ordered_children(children: &[Rc<RefCell<MenuNode>>], recent: Option<&History>) -> Vec<Rc<RefCell<MenuNode>>> {
+    let mut ordered = children.to_vec();
+    if let Some(history) = recent {
+        // Stable sort, so never-used entries keep their alphabetical order.
+        ordered.sort_by_cached_key(|child| std::cmp::Reverse(recency(&child.borrow(), history)));
+    }
+    ordered
+}
+
+/// Returns the top-level menu for a tab, or the whole tree if it has no such child.
+fn tab_root(menu_tree: &Rc<RefCell<MenuNode>>, tab: usize) -> Rc<RefCell<MenuNode>> {
+    menu_tree.borrow().children().and_then(|children| children.get(tab).cloned()).unwrap_or_else(|| menu_tree.clone())
 }
 
 impl App {
-    /// Creates a new App instance with default values.
-    fn new() -> App {
-        let os_distro = detect_os();
-        let menu_tree = scripts::build_menu_tree(os_distro);
-        let nav_path = vec![menu_tree.clone()];
+    /// Creates the App for this machine: detects the distro and probes enabled repositories.
+    fn new(args: &cli::CliArgs) -> App {
+        let os = detect_os();
+        let arch = Arch::host();
+        let mut app = App::with_menu_tree(os, scripts::build_menu_tree(os, arch, !args.no_defaults));
+        app.arch = arch;
+        app.assume_yes = args.assume_yes;
+        app.wrap_navigation = !args.no_wrap;
+        app.ascii = args.ascii || matches!(env::var("TERM").as_deref(), Ok("linux") | Ok("dumb"));
+        app.syntax_highlight = env::var_os("NO_COLOR").is_none_or(|value| value.is_empty());
+        app.enabled_repos = dnf::enabled_repos().unwrap_or_default();
+        app.history = History::load();
+        app.debug = args.debug;
+        app.show_ids = args.debug;
+        app.set_os_version(detect_os_version());
+        // Probing /usr/bin only says something about a script that runs here.
+        if args.deploy.is_none() {
+            app.host_package_manager = PackageManager::detect(app.os_version);
+        }
+        app.script_ctx.package_manager = app.host_package_manager;
+        scripts::mark_unsupported(&app.menu_tree, &app.script_ctx);
+        app.script_ctx.proxy = args.proxy.clone();
+        // A deployed script runs as the remote user, whatever this one is.
+        app.privilege = if args.deploy.is_some() { Privilege::Sudo } else { Privilege::detect() };
+        app.over_ssh = args.deploy.is_some() || env::var_os("SSH_CONNECTION").is_some_and(|value| !value.is_empty());
+        // A profile file that does not exist yet is created by the first [p].
+        if let Some(path) = args.profile.as_ref().filter(|path| path.exists()) {
+            match TomlProfile::read(path) {
+                Ok(profile) => {
+                    let problems = app.apply_toml_profile(&profile.items);
+                    if !problems.is_empty() {
+                        let body = format!("Some entries of {} were not applied:\n\n{}", path.display(), problems.join("\n"));
+                        app.push_info("Profile", body);
+                    }
+                }
+                Err(err) => app.show_error(err),
+            }
+        }
+        app.profile_path = args.profile.clone();
+        app
+    }
+
+    /// Creates an App with default settings and nothing selected for `os`, for tests.
+    #[cfg(test)]
+    fn for_target(os_distro: OsDistribution) -> App {
+        App::with_menu_tree(os_distro, scripts::build_menu_tree(os_distro, Arch::default(), false))
+    }
+
+    /// Creates an App with default settings around `menu_tree` without touching the system or terminal.
+    fn with_menu_tree(os_distro: OsDistribution, menu_tree: Rc<RefCell<MenuNode>>) -> App {
+        let nav_path = vec![tab_root(&menu_tree, 0)];
 
         App {
             state: AppState::Running,
@@ -133,239 +846,2155 @@ impl App {
             nav_path,
             selected_index: 0,
             os_distro,
+            detected_os: os_distro,
+            os_version: None,
+            host_package_manager: PackageManager::default(),
+            arch: Arch::default(),
+            output_format: OutputFormat::Bash,
             reboot_requested: false,
-            filename_input: String::new(),
+            filename_input: TextInput::default(),
             save_status_message: None,
+            no_sudo: false,
+            assume_yes: false,
+            previous_state: AppState::Running,
+            show_help: false,
+            show_preview: true,
+            wrap_navigation: true,
+            script_ctx: ScriptContext::default(),
+            settings_form: None,
+            info_popup: None,
+            detail_popup: None,
+            detail_scroll: 0,
+            last_run: None,
+            change_report: Vec::new(),
+            report_scroll: 0,
+            ascii: false,
+            syntax_highlight: false,
+            allow_erasing: false,
+            nobest: false,
+            index_stack: Vec::new(),
+            forward_stack: Vec::new(),
+            finished_index: 0,
+            show_full_script: false,
+            save_and_run: false,
+            split_output: false,
+            over_ssh: false,
+            privilege: Privilege::default(),
+            run_saved_path: None,
+            saved_paths: Vec::new(),
+            profile_path: None,
+            two_phase: false,
+            active_tab: 0,
+            category_filter: None,
+            tag_filter: BTreeSet::new(),
+            tag_index: 0,
+            flat_view: false,
+            debug: false,
+            show_ids: false,
+            recent_first: false,
+            history: History::default(),
+            enabled_repos: HashSet::new(),
+            note_target: None,
+            note_input: TextInput::default(),
+            reboot_at: None,
+            reboot_at_input: TextInput::default(),
+            schedule_input: TextInput::default(),
+            search_input: TextInput::default(),
+            version_pins: HashMap::new(),
+            no_auto_include: HashSet::new(),
+            pin_form: None,
+            group_excludes: HashMap::new(),
+            group_members: HashMap::new(),
+            member_picker: None,
+            group_lookup: None,
+            crash_recovery: false,
+            recovery_due: None,
+            recovered: None,
+            exclude_target: None,
+            exclude_input: TextInput::default(),
+            profile_manager: None,
+            cached_script: None,
+            cached_reboot: false,
+            dirty: true,
+            edited_script: None,
+            log: None,
         }
     }
 
-    /// Generates the shell commands, ensuring repos are first and adding error checks.
-    fn generate_commands(&self, reboot: bool) -> String {
-        let mut items = Vec::new();
-        self.menu_tree.borrow().get_selected_items_info(&mut items);
+    /// Names of the top-level menus, shown as tabs.
+    fn tab_titles(&self) -> Vec<String> {
+        match self.menu_tree.borrow().children() {
+            Some(children) => children.iter().map(|child| child.borrow().name().to_string()).collect(),
+            None => Vec::new(),
+        }
+    }
 
-        // Partition items into categories
-        let repos: Vec<&SelectedItem> = items.iter().filter(|i| i.category == ScriptCategory::Repository).collect();
-        let general: Vec<&SelectedItem> = items.iter().filter(|i| i.category == ScriptCategory::General).collect();
+    /// Switches to the tab `delta` positions away (wrapping) and resets navigation to its root.
+    fn switch_tab(&mut self, delta: isize) {
+        let count = self.tab_titles().len();
+        if count == 0 {
+            return;
+        }
+        self.active_tab = (self.active_tab as isize + delta).rem_euclid(count as isize) as usize;
+        self.nav_path = vec![tab_root(&self.menu_tree, self.active_tab)];
+        self.index_stack.clear();
+        self.forward_stack.clear();
+        self.selected_index = 0;
+    }
 
-        let mut command_text = String::new();
-        command_text.push_str("#!/bin/bash\n");
-        command_text.push_str(&format!("# Generated for {:?} by Enterprise Linux TUI\n\n", self.os_distro));
-        
-        // Add robust error handling and a logging function
-        command_text.push_str("# Exit immediately if a command exits with a non-zero status.\nset -e\n\n");
-        command_text.push_str("# Helper for logging steps\nprint_step() {\n    echo\n    echo \"✅ ==> $1\"\n}\n\n");
+    /// Records the detected EL major version and points the dnf syntax and the EPEL release at it.
+    fn set_os_version(&mut self, version: Option<u32>) {
+        self.os_version = version;
+        self.script_ctx.dnf_flavor = DnfFlavor::for_version(self.target_version());
+        self.script_ctx.epel_version = self.target_version().unwrap_or(scripts::FALLBACK_EL_VERSION);
+    }
 
-        if repos.is_empty() && general.is_empty() {
-            command_text.push_str("# No options selected.\n");
-        }
+    /// The EL major version of the target distro; only known while that is the detected one.
+    fn target_version(&self) -> Option<u32> {
+        if self.os_distro == self.detected_os { self.os_version } else { None }
+    }
 
-        // 1. Add repository scripts first
-        if !repos.is_empty() {
-            command_text.push_str("# --- 1. ENABLING REPOSITORIES ---\n");
-            for item in &repos {
-                command_text.push_str(&format!("print_step \"{}\"\n", item.name));
-                command_text.push_str((item.script_fn)());
-                command_text.push_str("\n");
-            }
-        }
+    /// Rebuilds the menu for another target distro, keeping selections and notes by item id.
+    /// Navigation is reset to the root of the active tab.
+    fn rebuild_for(&mut self, os: OsDistribution) {
+        let mut ids = HashSet::new();
+        self.menu_tree.borrow().selected_ids(&mut ids);
+        let mut notes = HashMap::new();
+        self.menu_tree.borrow().notes(&mut notes);
 
-        // 2. Add all other general scripts
-        if !general.is_empty() {
-            command_text.push_str("\n# --- 2. APPLYING CONFIGURATIONS ---\n");
-            for item in &general {
-                command_text.push_str(&format!("print_step \"{}\"\n", item.name));
-                command_text.push_str((item.script_fn)());
-                command_text.push_str("\n");
-            }
+        let previous_epel = self.target_version().unwrap_or(scripts::FALLBACK_EL_VERSION);
+        self.os_distro = os;
+        self.script_ctx.package_manager = if os == self.detected_os { self.host_package_manager } else { PackageManager::default() };
+        self.script_ctx.dnf_flavor = DnfFlavor::for_version(self.target_version());
+        // An EPEL release picked in the settings is kept.
+        if self.script_ctx.epel_version == previous_epel {
+            self.script_ctx.epel_version = self.target_version().unwrap_or(scripts::FALLBACK_EL_VERSION);
         }
+        self.menu_tree = scripts::build_menu_tree(os, self.arch, false);
+        scripts::mark_unsupported(&self.menu_tree, &self.script_ctx);
+        self.menu_tree.borrow_mut().select_ids(&ids);
+        self.menu_tree.borrow_mut().restore_notes(&notes);
+        self.invalidate_script();
 
-        if reboot {
-            command_text.push_str("\nprint_step \"All tasks complete. Rebooting now...\"\n");
-            command_text.push_str("sleep 3\n");
-            command_text.push_str("sudo reboot\n");
-        } else if !repos.is_empty() || !general.is_empty() {
-            command_text.push_str("\nprint_step \"All tasks complete!\"\n");
+        self.active_tab = self.active_tab.min(self.tab_titles().len().saturating_sub(1));
+        self.nav_path = vec![tab_root(&self.menu_tree, self.active_tab)];
+        self.index_stack.clear();
+        self.forward_stack.clear();
+        self.selected_index = 0;
+    }
+
+    /// Replaces the selection, notes and run options with those of `profile`.
+    /// Fails without changing anything if the profile names an unknown item.
+    fn apply_profile(&mut self, profile: &Profile) -> Result<(), String> {
+        let mut all = Vec::new();
+        self.menu_tree.borrow().collect_items(None, &mut all, true);
+        let known = |name: &str| all.iter().map(|item| item.id).find(|id| *id == name);
+
+        let unknown: Vec<&str> = profile.items.iter().chain(profile.notes.keys())
+            .map(String::as_str)
+            .filter(|name| known(name).is_none())
+            .collect();
+        if !unknown.is_empty() {
+            return Err(format!("unknown item ids in profile: {}", unknown.join(", ")));
         }
 
-        command_text
-    }
-    
-    /// Gets just the names of selected items for display in the UI.
-    fn get_selected_items(&self) -> Vec<String> {
-        let mut items_info = Vec::new();
-        self.menu_tree.borrow().get_selected_items_info(&mut items_info);
-        items_info.into_iter().map(|i| i.name).collect()
+        let ids: HashSet<&'static str> = profile.items.iter().filter_map(|name| known(name)).collect();
+        let notes: HashMap<&'static str, String> = profile.notes.iter()
+            .filter_map(|(name, note)| Some((known(name)?, note.clone())))
+            .collect();
+        let reboot_at = profile.reboot_at.as_deref().map(parse_reboot_time).transpose()?;
+        self.menu_tree.borrow_mut().select_ids(&ids);
+        self.menu_tree.borrow_mut().restore_notes(&notes);
+        self.reboot_requested = profile.reboot;
+        self.reboot_at = reboot_at;
+        self.no_sudo = profile.no_sudo;
+        self.invalidate_script();
+        Ok(())
     }
-}
-
-fn main() -> Result<(), Box<dyn Error>> {
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
 
-    let app = App::new();
-    let res = run_app(&mut terminal, app);
+    /// Numbers of selected repository (including COPR) and general items.
+    fn selection_counts(&self) -> (usize, usize) {
+        let mut items = Vec::new();
+        self.menu_tree.borrow().get_selected_items_info(&mut items);
+        let repos = items.iter().filter(|item| item.category != ScriptCategory::General).count();
+        (repos, items.len() - repos)
+    }
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
-    terminal.show_cursor()?;
+    /// The current selection, notes and run options as a profile.
+    fn current_profile(&self) -> Profile {
+        let mut ids = HashSet::new();
+        let mut notes = HashMap::new();
+        let tree = self.menu_tree.borrow();
+        tree.selected_ids(&mut ids);
+        tree.notes(&mut notes);
+        let mut items: Vec<String> = ids.into_iter().map(str::to_string).collect();
+        items.sort();
+        Profile {
+            items,
+            notes: notes.into_iter().map(|(id, note)| (id.to_string(), note)).collect(),
+            reboot: self.reboot_requested,
+            reboot_at: self.reboot_at.clone(),
+            no_sudo: self.no_sudo,
+        }
+    }
 
-    if let Ok(ActionAfterExit::RunScript(script_content)) = res {
-        let script_path = "/tmp/tui_install_script.sh";
-        println!("Saving temporary script to {}...", script_path);
-        fs::write(script_path, &script_content)?;
-        fs::set_permissions(script_path, fs::Permissions::from_mode(0o755))?;
+    /// Shows only items of `filter` across the whole tree, or the normal tree for `None`.
+    fn set_category_filter(&mut self, filter: Option<ScriptCategory>) {
+        self.category_filter = filter;
+        self.selected_index = 0;
+    }
 
-        println!("Exited TUI. Now attempting to run the script with sudo...");
-        println!("--- SCRIPT ---");
-        println!("{}", script_content);
-        println!("--------------");
-        
-        let status = Command::new("sudo").arg("bash").arg(script_path).status()?;
+    /// Every tag used in the menu, in the order the tag picker lists them.
+    fn all_tags(&self) -> Vec<&'static str> {
+        let mut tags = BTreeSet::new();
+        self.menu_tree.borrow().tags(&mut tags);
+        tags.into_iter().collect()
+    }
 
-        if status.success() {
-            println!("\nScript executed successfully.");
-        } else {
-            println!("\nScript execution failed. Please check the output above.");
+    /// Memoized `generate_commands`: regenerates only when dirty or asked for the other reboot variant.
+    fn script(&mut self, reboot: bool) -> String {
+        if let Some((edited_reboot, text)) = &self.edited_script
+            && *edited_reboot == reboot
+            && self.output_format == OutputFormat::Bash
+        {
+            return text.clone();
+        }
+        if self.dirty || self.cached_reboot != reboot || self.cached_script.is_none() {
+            // Only the Bash script runs on the host the package manager was detected for; the
+            // other formats build an image or set up machines whose manager is not known here.
+            let package_manager = self.script_ctx.package_manager;
+            if self.output_format != OutputFormat::Bash {
+                self.script_ctx.package_manager = PackageManager::default();
+            }
+            let script = match self.output_format {
+                OutputFormat::Bash => self.generate_commands(reboot),
+                OutputFormat::Containerfile => self.generate_containerfile(),
+                OutputFormat::CloudInit => self.generate_cloud_init(reboot),
+                OutputFormat::Bundle => self.generate_bundle(reboot),
+            };
+            self.script_ctx.package_manager = package_manager;
+            log_event!(self, "generated script: {} bytes, reboot={}", script.len(), reboot);
+            self.cached_script = Some(script);
+            self.cached_reboot = reboot;
+            self.dirty = false;
         }
-        fs::remove_file(script_path)?;
-    } else if let Err(err) = res {
-        println!("{:?}", err)
+        self.cached_script.clone().unwrap_or_default()
     }
 
-    Ok(())
-}
-
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<ActionAfterExit> {
-    loop {
-        terminal.draw(|f| ui(f, &mut app))?;
+    /// Marks the cached script stale. Call after any change that affects the generated script.
+    fn invalidate_script(&mut self) {
+        self.dirty = true;
+        self.edited_script = None;
+        if self.crash_recovery {
+            self.recovery_due.get_or_insert_with(Instant::now);
+        }
+    }
 
-        if let Event::Key(key) = event::read()? {
-            match app.state {
-                AppState::Running => {
-                    let visible_nodes = get_visible_nodes(&app.nav_path);
-                    let visible_len = visible_nodes.len();
+    /// Flips an item's selection. Selecting an item in a radio group deselects its siblings.
+    fn toggle_item(&mut self, item: &Rc<RefCell<MenuNode>>) {
+        let selected = match &*item.borrow() {
+            MenuNode::Item { selected, .. } => *selected,
+            _ => return,
+        };
+        self.set_item_selected(item, !selected);
+    }
 
-                    if visible_len > 0 {
+    /// Selects or clears an available item, logging the change and recording selections in the
+    /// history. Selecting a radio option clears its siblings.
+    fn set_item_selected(&mut self, item: &Rc<RefCell<MenuNode>>, value: bool) {
+        match &mut *item.borrow_mut() {
+            MenuNode::Item { availability: Availability::Unavailable(_), .. } => return,
+            MenuNode::Item { selected, .. } if *selected == value => return,
+            MenuNode::Item { id, selected, .. } => {
+                *selected = value;
+                log_event!(self, "toggle {} -> {}", id, selected);
+                if value {
+                    self.history.record(id);
+                }
+            }
+            _ => return,
+        }
+        self.invalidate_script();
+        if !value {
+            return;
+        }
+        if let Some(parent) = find_parent(&self.menu_tree, item)
+            && let MenuNode::Radio { children, .. } = &*parent.borrow()
+        {
+            for sibling in children.iter().filter(|child| !Rc::ptr_eq(child, item)) {
+                if let MenuNode::Item { selected, .. } = &mut *sibling.borrow_mut() {
+                    *selected = false;
+                }
+            }
+        }
+    }
+
+    /// Selects or clears every available item below `node`. Selecting leaves radio groups
+    /// alone, since only one of their options may be chosen; clearing empties them too.
+    fn set_subtree_selection(&mut self, node: &Rc<RefCell<MenuNode>>, value: bool) {
+        let children = match &*node.borrow() {
+            MenuNode::Item { .. } => None,
+            MenuNode::Radio { .. } if value => return,
+            MenuNode::Menu { children, .. } | MenuNode::Radio { children, .. } => Some(children.clone()),
+        };
+        match children {
+            None => self.set_item_selected(node, value),
+            Some(children) => {
+                for child in &children {
+                    self.set_subtree_selection(child, value);
+                }
+            }
+        }
+    }
+
+    /// Whether the menu shows a flat list of matches from the whole tree instead of the current
+    /// menu: while searching, filtering by category or tag, or in the flat view.
+    fn shows_matches(&self) -> bool {
+        !self.search_input.value.trim().is_empty() || self.category_filter.is_some() || self.flat_view || !self.tag_filter.is_empty()
+    }
+
+    /// Inverts the selection of the items in view: the rows of a filtered list, or everything
+    /// below the current menu otherwise.
+    fn invert_selection(&mut self, visible_nodes: &[(String, Rc<RefCell<MenuNode>>)]) {
+        let mut items = Vec::new();
+        if self.shows_matches() {
+            for (_, node) in visible_nodes {
+                let in_radio = find_parent(&self.menu_tree, node).is_some_and(|parent| matches!(&*parent.borrow(), MenuNode::Radio { .. }));
+                if matches!(&*node.borrow(), MenuNode::Item { .. }) && !in_radio {
+                    invertible_items(node, &mut items);
+                }
+            }
+        } else if let Some(menu) = self.nav_path.last() {
+            invertible_items(menu, &mut items);
+        }
+        for item in &items {
+            let selected = matches!(&*item.borrow(), MenuNode::Item { selected: true, .. });
+            self.set_item_selected(item, !selected);
+        }
+    }
+
+    /// Selects exactly the items a `--profile` file names and returns the entries it could not
+    /// apply. Entries are menu-qualified names; a bare item name is accepted only while it is
+    /// unique, since names such as "Base Installation" repeat across menus.
+    fn apply_toml_profile(&mut self, names: &[String]) -> Vec<String> {
+        let mut items = Vec::new();
+        item_nodes(&self.menu_tree, None, &mut items);
+        for (_, node) in &items {
+            self.set_item_selected(node, false);
+        }
+        let mut problems = Vec::new();
+        for name in names {
+            let matching: Vec<&(String, Rc<RefCell<MenuNode>>)> = match items.iter().find(|(qualified, _)| qualified == name) {
+                Some(item) => vec![item],
+                None => items.iter().filter(|(_, node)| node.borrow().name() == name).collect(),
+            };
+            match matching.as_slice() {
+                [] => problems.push(format!("{}: no such item", name)),
+                [(_, node)] => self.set_item_selected(node, true),
+                several => {
+                    let choices: Vec<&str> = several.iter().map(|(qualified, _)| qualified.as_str()).collect();
+                    problems.push(format!("{}: matches {} items, use one of\n  {}", name, several.len(), choices.join("\n  ")));
+                }
+            }
+        }
+        problems
+    }
+
+    /// Shows `body` in the info popup, below any notice already waiting there.
+    fn push_info(&mut self, title: &str, body: String) {
+        match &mut self.info_popup {
+            Some((_, existing)) => {
+                existing.push_str("\n\n");
+                existing.push_str(&body);
+            }
+            None => self.info_popup = Some((title.to_string(), body)),
+        }
+    }
+
+    /// Enters a submenu, remembering the cursor of the current level.
+    fn descend(&mut self, menu: Rc<RefCell<MenuNode>>) {
+        self.forward_stack.clear();
+        self.index_stack.push(self.selected_index);
+        self.nav_path.push(menu);
+        self.selected_index = 0;
+    }
+
+    /// Returns to the parent menu and records the current one for `go_forward`.
+    fn go_back(&mut self) {
+        if self.nav_path.len() > 1 {
+            let menu = self.nav_path.pop().unwrap();
+            self.forward_stack.push((menu, self.selected_index));
+            self.selected_index = self.index_stack.pop().unwrap_or(0);
+        }
+    }
+
+    /// Re-enters the menu most recently left via `go_back`, restoring its cursor.
+    fn go_forward(&mut self) {
+        if let Some((menu, index)) = self.forward_stack.pop() {
+            self.index_stack.push(self.selected_index);
+            self.nav_path.push(menu);
+            self.selected_index = index;
+        }
+    }
+
+    /// Asks for confirmation, or returns the accepted action right away when `--yes` was given.
+    fn confirm(&mut self, confirmation: Confirmation) -> Option<ActionAfterExit> {
+        if self.assume_yes {
+            return self.accept(confirmation);
+        }
+        self.previous_state = self.state.clone();
+        self.state = AppState::Confirm(confirmation);
+        None
+    }
+
+    /// The script for display. Bash output gets section rules, and syntax highlighting when enabled.
+    fn script_text(&mut self, reboot: bool) -> Text<'static> {
+        let script = self.script(reboot);
+        if self.output_format.is_bash() {
+            highlight::display(&script, self.syntax_highlight)
+        } else {
+            Text::from(script)
+        }
+    }
+
+    /// Shows `message` in the status popup until it expires.
+    fn set_status(&mut self, message: String) {
+        self.save_status_message = Some((message, Instant::now()));
+    }
+
+    /// Called on every loop iteration: drops the status message once it has been shown long enough,
+    /// autosaves the selection once it has been left alone for `RECOVERY_DEBOUNCE`, and picks up
+    /// a finished group lookup.
+    fn tick(&mut self) {
+        let finished = match self.group_lookup.as_ref().map(|lookup| lookup.result.try_recv()) {
+            Some(Ok(result)) => Some(result),
+            Some(Err(mpsc::TryRecvError::Disconnected)) => Some(Err("The dnf group info lookup stopped unexpectedly.".to_string())),
+            Some(Err(mpsc::TryRecvError::Empty)) | None => None,
+        };
+        if let Some(result) = finished
+            && let Some(lookup) = self.group_lookup.take()
+        {
+            self.finish_group_lookup(lookup, result);
+        }
+        if self.save_status_message.as_ref().is_some_and(|(_, shown)| shown.elapsed() >= STATUS_MESSAGE_DURATION) {
+            self.save_status_message = None;
+        }
+        if let Some(changed) = self.recovery_due {
+            // The in-memory snapshot stays current so a panic can still save the latest change.
+            recovery::record(&self.current_profile());
+            if changed.elapsed() >= RECOVERY_DEBOUNCE {
+                recovery::flush();
+                self.recovery_due = None;
+            }
+        }
+    }
+
+    /// Shows an error modal; dismissing it returns to the current screen.
+    fn show_error(&mut self, message: String) {
+        if !matches!(self.state, AppState::Error(_)) {
+            self.previous_state = self.state.clone();
+        }
+        self.state = AppState::Error(message);
+    }
+
+    /// The action to take once a confirmation has been accepted, or `None` when it only changes
+    /// the session and the TUI keeps running.
+    fn accept(&mut self, confirmation: Confirmation) -> Option<ActionAfterExit> {
+        match confirmation {
+            Confirmation::Quit => Some(ActionAfterExit::Quit(self.exit_summary())),
+            Confirmation::RunWithReboot => Some(ActionAfterExit::RunScript {
+                script: self.script(true),
+                saved_path: self.run_saved_path.clone(),
+                two_phase: self.two_phase,
+            }),
+            Confirmation::RestoreSession => {
+                if let Some(profile) = self.recovered.take()
+                    && let Err(err) = self.apply_profile(&profile)
+                {
+                    self.show_error(format!("Cannot restore the previous session: {}", err));
+                }
+                None
+            }
+        }
+    }
+
+    /// Generates the shell commands, ensuring repos are first and adding error checks.
+    fn generate_commands(&self, reboot: bool) -> String {
+        self.render_script(reboot, true, true)
+    }
+
+    /// Generates one standalone script per category, keyed by the file suffix ("repos" or
+    /// "packages"). Categories without selected items are left out. The reboot, if any, goes
+    /// at the end of the packages script.
+    fn generate_split(&self, reboot: bool) -> BTreeMap<&'static str, String> {
+        let (items, _) = self.resolved_items();
+        let mut scripts = BTreeMap::new();
+        if items.iter().any(|i| i.category != ScriptCategory::General) {
+            scripts.insert("repos", self.render_script(false, true, false));
+        }
+        if items.iter().any(|i| i.category == ScriptCategory::General) {
+            scripts.insert("packages", self.render_script(reboot, false, true));
+        }
+        scripts
+    }
+
+    /// Renders the script with the repository phase, the configuration phase, or both.
+    fn render_script(&self, reboot: bool, with_repos: bool, with_general: bool) -> String {
+        let (items, dependency_error) = self.resolved_items();
+
+        // Partition items into categories. COPR enables are ordered with the other repositories.
+        let all_repos: Vec<&SelectedItem> = items.iter()
+            .filter(|i| matches!(i.category, ScriptCategory::Repository | ScriptCategory::Copr))
+            .collect();
+        // dnf tuning belongs with the repositories, or stands alone when there are none.
+        let with_tuning = with_repos || all_repos.is_empty();
+        let repos = if with_repos { all_repos } else { Vec::new() };
+        let general: Vec<&SelectedItem> = items.iter()
+            .filter(|i| with_general && i.category == ScriptCategory::General)
+            .collect();
+
+        let mut command_text = String::new();
+        command_text.push_str("#!/bin/bash\n");
+        command_text.push_str(&format!("# Generated for {:?} by Enterprise Linux TUI\n", self.os_distro));
+        command_text.push_str(&format!("# Package manager: {}\n\n", self.script_ctx.package_manager));
+        if self.no_sudo {
+            command_text.push_str("# No-sudo mode: flatpak operations run per-user (--user) without sudo.\n");
+            command_text.push_str("# Steps marked 'requires root' below still need sudo privileges.\n\n");
+        }
+        
+        if self.privilege == Privilege::Root {
+            command_text.push_str("# Generated as root: commands run without sudo.\n\n");
+        }
+        if let Some(err) = &dependency_error {
+            command_text.push_str(&format!("# WARNING: {}; prerequisites were not added.\n\n", err));
+        }
+
+        // Add robust error handling and a logging function
+        command_text.push_str("# Exit immediately if a command exits with a non-zero status.\nset -e\n\n");
+        command_text.push_str("# The user who invoked the script, not root when it runs through sudo.\nTARGET_USER=\"${SUDO_USER:-$USER}\"\n\n");
+        let step_marker = if self.ascii { "==>" } else { "✅ ==>" };
+        if self.script_ctx.record_run {
+            command_text.push_str(&format!("# Helper for logging steps; it also closes the previous step in the run log\nprint_step() {{\n    record_step 0\n    echo\n    echo \"{} $1\"\n    STEP_NAME=\"$1\"\n    STEP_START=$SECONDS\n}}\n\n", step_marker));
+            command_text.push_str(RUN_LOG_SECTION);
+        } else {
+            command_text.push_str(&format!("# Helper for logging steps\nprint_step() {{\n    echo\n    echo \"{} $1\"\n}}\n\n", step_marker));
+        }
+        if let Some(proxy) = &self.script_ctx.proxy {
+            command_text.push_str(&proxy_section(proxy));
+        }
+
+        if repos.is_empty() && general.is_empty() {
+            command_text.push_str("# No options selected.\n");
+        } else if let Some(parallel) = self.script_ctx.parallel_downloads.filter(|_| with_tuning) {
+            // dnf4 has no drop-in directory, so dnf.conf is edited in place after a one-time backup.
+            command_text.push_str("# --- DNF TUNING ---\n");
+            command_text.push_str("print_step \"Tuning dnf.conf (backup: /etc/dnf/dnf.conf.el-init.bak)\"\n");
+            command_text.push_str("sudo cp -n /etc/dnf/dnf.conf /etc/dnf/dnf.conf.el-init.bak\n");
+            command_text.push_str("sudo sed -i '/^max_parallel_downloads=/d;/^fastestmirror=/d' /etc/dnf/dnf.conf\n");
+            command_text.push_str(&format!("printf 'max_parallel_downloads={}\\nfastestmirror=True\\n' | sudo tee -a /etc/dnf/dnf.conf > /dev/null\n\n", parallel));
+        }
+
+        // Originals are saved before the first step that could change them. The backups sit
+        // inside the first section so that a two-phase run makes them only once.
+        let backups = self.config_backups(repos.iter().chain(&general).copied());
+        let plugins = if self.needs_plugins(repos.iter().chain(&general).copied().filter(|item| !self.already_enabled(item))) {
+            let package = self.script_ctx.plugins_package();
+            format!("# config-manager and copr come from {0}, which minimal installs lack.\nrpm -q {0} > /dev/null 2>&1 || sudo {1} install -y {0}\n",
+                package, self.script_ctx.package_manager)
+        } else {
+            String::new()
+        };
+
+        // 1. Add repository scripts first
+        if !repos.is_empty() {
+            command_text.push_str(runner::REPOS_SECTION);
+            command_text.push('\n');
+            command_text.push_str(&backups);
+            command_text.push_str(&plugins);
+            for item in &repos {
+                if self.os_distro == OsDistribution::Rhel {
+                    push_rhel_repo_warnings(&mut command_text, &(item.script_fn)(&self.script_ctx));
+                }
+                self.push_item_script(&mut command_text, item);
+            }
+            command_text.push_str(runner::PHASE_BOUNDARY);
+            command_text.push('\n');
+        }
+
+        // 2. Add all other general scripts
+        if !general.is_empty() {
+            command_text.push_str("\n# --- 2. APPLYING CONFIGURATIONS ---\n");
+            if repos.is_empty() {
+                command_text.push_str(&backups);
+                command_text.push_str(&plugins);
+            }
+            // Items often share a final step (e.g. enabling cockpit.socket); it only needs to run once.
+            let mut seen = HashSet::new();
+            for item in &general {
+                command_text.push_str(&dedupe_commands(&self.item_script(item), &mut seen));
+            }
+        }
+
+        if general.iter().any(|item| item.wayland_only) {
+            command_text.push_str("\n# --- SESSION REQUIREMENTS ---\n");
+            command_text.push_str("print_step \"Session requirements\"\n");
+            command_text.push_str("echo 'Sway is a Wayland compositor: start it with \"sway\" from a TTY or choose the Sway session at login.'\n");
+            command_text.push_str("echo 'X11-only applications run inside it through Xwayland.'\n");
+        }
+
+        if reboot && let Some(time) = &self.reboot_at {
+            command_text.push_str(&format!("\nprint_step \"All tasks complete. Reboot scheduled for {} (cancel with: sudo shutdown -c).\"\n", time));
+            command_text.push_str(&format!("sudo shutdown -r {}\n", time));
+        } else if reboot && self.over_ssh {
+            // A transient timer lets the script exit and the SSH session close before the reboot.
+            command_text.push_str("\nprint_step \"All tasks complete. Rebooting in 10 seconds; this SSH session will close.\"\n");
+            command_text.push_str("sudo systemd-run --on-active=10 --timer-property=AccuracySec=1s systemctl reboot\n");
+        } else if reboot {
+            command_text.push_str("\nprint_step \"All tasks complete. Rebooting now...\"\n");
+            command_text.push_str("sleep 3\n");
+            command_text.push_str("sudo reboot\n");
+        } else if !repos.is_empty() || !general.is_empty() {
+            command_text.push_str("\nprint_step \"All tasks complete!\"\n");
+        }
+
+        if self.privilege == Privilege::Root {
+            return runner::strip_sudo(&command_text);
+        }
+        command_text
+    }
+    
+    /// A script for every target distro at once. Only the repository section differs between
+    /// them, so it becomes a `case` on the os-release ID with one branch per distinct variant.
+    fn generate_bundle(&mut self, reboot: bool) -> String {
+        let script = self.render_script(reboot, true, true)
+            .replacen(&format!("# Generated for {:?} by", self.os_distro), "# Generated for all supported EL distros by", 1);
+        let (Some(start), Some(end)) = (script.find(runner::REPOS_SECTION), script.find(runner::PHASE_BOUNDARY)) else {
+            return script;
+        };
+
+        let mut ids = HashSet::new();
+        self.menu_tree.borrow().selected_ids(&mut ids);
+        let mut notes = HashMap::new();
+        self.menu_tree.borrow().notes(&mut notes);
+        let (os_distro, menu_tree) = (self.os_distro, self.menu_tree.clone());
+        let mut branches: Vec<(Vec<&str>, String)> = Vec::new();
+        for os in OsDistribution::TARGETS {
+            self.os_distro = os;
+            self.menu_tree = scripts::build_menu_tree(os, self.arch, false);
+            self.menu_tree.borrow_mut().select_ids(&ids);
+            self.menu_tree.borrow_mut().restore_notes(&notes);
+            // With the general items too, so the backups in the repository section cover them.
+            let variant = self.render_script(false, true, true);
+            let section = match (variant.find(runner::REPOS_SECTION), variant.find(runner::PHASE_BOUNDARY)) {
+                (Some(start), Some(end)) => variant[start + runner::REPOS_SECTION.len()..end].trim().to_string(),
+                _ => ":".to_string(),
+            };
+            match branches.iter_mut().find(|(_, body)| *body == section) {
+                Some((distros, _)) => distros.push(os.id()),
+                None => branches.push((vec![os.id()], section)),
+            }
+        }
+        self.os_distro = os_distro;
+        self.menu_tree = menu_tree;
+
+        let supported = OsDistribution::TARGETS.iter().map(|os| os.id()).collect::<Vec<_>>().join(", ");
+        let mut section = String::from("\n# CentOS Stream shares ID=centos with CentOS Linux; only NAME tells them apart.\n");
+        section.push_str(". /etc/os-release\nEL_DISTRO=\"$ID\"\n");
+        section.push_str("if [ \"$ID\" = centos ] && [[ \"$NAME\" == *Stream* ]]; then EL_DISTRO=centos-stream; fi\n");
+        section.push_str("case \"$EL_DISTRO\" in\n");
+        for (distros, body) in &branches {
+            section.push_str(&format!("{})\n{}\n;;\n", distros.join("|"), body));
+        }
+        section.push_str(&format!("*)\necho \"No repository steps for '$EL_DISTRO'; supported: {}\" >&2\nexit 1\n;;\nesac\n", supported));
+        format!("{}{}{}", &script[..start + runner::REPOS_SECTION.len()], section, &script[end..])
+    }
+
+    /// The selected items plus their prerequisites, with prerequisites ordered first.
+    /// On a dependency cycle, returns the plain selection and a description of the cycle.
+    fn resolved_items(&self) -> (Vec<SelectedItem>, Option<String>) {
+        let mut selected = Vec::new();
+        self.menu_tree.borrow().get_selected_items_info(&mut selected);
+        let mut all = Vec::new();
+        self.menu_tree.borrow().collect_items(None, &mut all, true);
+
+        let ids: Vec<&'static str> = selected.iter().map(|item| item.id).collect();
+        let requires = |id: &str| all.iter().find(|item| item.id == id).map_or(&[][..], |item| item.requires);
+        let may_add = |id: &str| !self.no_auto_include.contains(id);
+        match deps::resolve(&ids, requires, may_add) {
+            Ok(order) => {
+                let items = order.into_iter().filter_map(|(id, auto_added)| {
+                    let item = all.iter().find(|item| item.id == id)?;
+                    Some(SelectedItem { auto_added, ..item.clone() })
+                }).collect();
+                (items, None)
+            }
+            Err(cycle) => (selected, Some(format!("dependency cycle {}", cycle.join(" -> ")))),
+        }
+    }
+
+    /// What running the script would change, as titled groups: repositories to enable or
+    /// already enabled, packages to install or already installed, and groups. `probe` reports
+    /// which of the package names are installed.
+    fn change_report(&self, probe: impl FnOnce(&[String]) -> Result<HashSet<String>, String>) -> Vec<(String, Vec<String>)> {
+        let (items, _) = self.resolved_items();
+        let (mut new_repos, mut enabled_repos) = (Vec::new(), Vec::new());
+        let (mut packages, mut groups) = (Vec::<String>::new(), Vec::<String>::new());
+        for item in &items {
+            let script = self.item_commands(item);
+            if item.category != ScriptCategory::General {
+                let repos = dnf::enabled_by(&script).unwrap_or_else(|| vec![item.name.clone()]);
+                if self.already_enabled(item) { enabled_repos.extend(repos) } else { new_repos.extend(repos) }
+            }
+            if (script.contains("groupinstall") || script.contains("group install"))
+                && let Some(group) = dnf::group_name(&script)
+            {
+                groups.push(format!("@{}", group));
+            }
+            for spec in dnf::install_specs(&script) {
+                let list = if spec.starts_with('@') { &mut groups } else { &mut packages };
+                if !list.contains(&spec) {
+                    list.push(spec);
+                }
+            }
+        }
+
+        let mut notes = Vec::new();
+        // The probe describes this machine, so it says nothing about another target distro.
+        let installed = if self.os_distro != self.detected_os {
+            notes.push(format!("Targeting {:?}, not this machine: installed packages were not checked.", self.os_distro));
+            HashSet::new()
+        } else {
+            probe(&packages).unwrap_or_else(|err| {
+                notes.push(format!("Could not check installed packages: {}", err));
+                HashSet::new()
+            })
+        };
+        let (skipped, to_install): (Vec<String>, Vec<String>) = packages.into_iter().partition(|name| installed.contains(name));
+        notes.push(format!("About {} packages to install, plus their dependencies and the members of {} groups.", to_install.len(), groups.len()));
+
+        vec![
+            (format!("Repositories to enable ({})", new_repos.len()), new_repos),
+            (format!("Repositories already enabled ({})", enabled_repos.len()), enabled_repos),
+            (format!("Packages to install ({})", to_install.len()), to_install),
+            (format!("Packages already installed ({})", skipped.len()), skipped),
+            (format!("Package groups ({})", groups.len()), groups),
+            ("Summary".to_string(), notes),
+        ]
+    }
+
+    /// Whether a repository item only enables repos that are already enabled here.
+    /// The probe describes this machine, so it is ignored when targeting another distro.
+    fn already_enabled(&self, item: &SelectedItem) -> bool {
+        if item.category == ScriptCategory::General || self.os_distro != self.detected_os {
+            return false;
+        }
+        dnf::enabled_by(&(item.script_fn)(&self.script_ctx))
+            .is_some_and(|ids| ids.iter().all(|id| self.enabled_repos.contains(id)))
+    }
+
+    /// Builds a Containerfile for the target distro with one `RUN` per component, repositories
+    /// first. Commands run as root during the build, so `sudo` is dropped; there is no reboot.
+    fn generate_containerfile(&self) -> String {
+        let (items, dependency_error) = self.resolved_items();
+        let version = self.target_version().unwrap_or(scripts::FALLBACK_EL_VERSION);
+        let image = match self.os_distro {
+            OsDistribution::Rocky => format!("docker.io/rockylinux/rockylinux:{}", version),
+            OsDistribution::AlmaLinux => format!("docker.io/library/almalinux:{}", version),
+            OsDistribution::Centos | OsDistribution::CentosStream => format!("quay.io/centos/centos:stream{}", version),
+            OsDistribution::Rhel | OsDistribution::GenericEl | OsDistribution::Unknown => {
+                format!("registry.access.redhat.com/ubi{}/ubi", version)
+            }
+        };
+
+        let mut text = String::new();
+        text.push_str("# syntax=docker/dockerfile:1\n");
+        text.push_str(&format!("# Generated for {:?} by Enterprise Linux TUI\n", self.os_distro));
+        if let Some(err) = &dependency_error {
+            text.push_str(&format!("# WARNING: {}; prerequisites were not added.\n", err));
+        }
+        text.push_str(&format!("FROM {}\n", image));
+        // Steps that configure the invoking user apply to root during the build.
+        text.push_str("ARG TARGET_USER=root\n");
+
+        let repos: Vec<&SelectedItem> = items.iter().filter(|i| i.category != ScriptCategory::General).collect();
+        let general = items.iter().filter(|i| i.category == ScriptCategory::General);
+        // Base images are minimal, so the config-manager and copr plugins are usually missing.
+        if self.needs_plugins(items.iter()) {
+            text.push_str(&format!("\nRUN {} install -y {}\n", self.script_ctx.package_manager, self.script_ctx.plugins_package()));
+        }
+        for item in repos.iter().copied().chain(general) {
+            text.push_str(&format!("\n# {}\n", item.qualified_name));
+            if let Some(note) = &item.note {
+                text.push_str(&format!("# note: {}\n", note));
+            }
+            let script = runner::strip_sudo_for_root_user(&self.item_commands(item));
+            let lines: Vec<&str> = script.lines().collect();
+            match lines.as_slice() {
+                [line] => text.push_str(&format!("RUN {}\n", line)),
+                // Multi-line steps may contain shell control flow, so they run as a heredoc.
+                lines => text.push_str(&format!("RUN <<'EOF'\nset -e\n{}\nEOF\n", lines.join("\n"))),
+            }
+        }
+
+        if !items.is_empty() {
+            text.push_str("\nRUN dnf clean all\n");
+        }
+        text
+    }
+
+    /// Builds cloud-init user-data with one `runcmd` entry per component, repositories first.
+    /// cloud-init runs as root, so `sudo` is dropped; a reboot becomes `power_state`.
+    fn generate_cloud_init(&self, reboot: bool) -> String {
+        let (items, dependency_error) = self.resolved_items();
+        let mut text = String::from("#cloud-config\n");
+        text.push_str(&format!("# Generated for {:?} by Enterprise Linux TUI\n", self.os_distro));
+        if let Some(err) = &dependency_error {
+            text.push_str(&format!("# WARNING: {}; prerequisites were not added.\n", err));
+        }
+
+        let repos: Vec<&SelectedItem> = items.iter().filter(|i| i.category != ScriptCategory::General).collect();
+        let general = items.iter().filter(|i| i.category == ScriptCategory::General);
+        // JSON strings are valid YAML double-quoted scalars, so serde_json does the escaping.
+        let quote = |value: &str| serde_json::to_string(value).unwrap_or_default();
+        let mut commands = Vec::new();
+        if self.needs_plugins(items.iter()) {
+            let plugins = self.script_ctx.plugins_package();
+            commands.push(format!("  - {}\n", quote(&format!("{} install -y {}", self.script_ctx.package_manager, plugins))));
+        }
+        for item in repos.iter().copied().chain(general) {
+            let mut entry = format!("  # {}\n", item.qualified_name);
+            if let Some(note) = &item.note {
+                entry.push_str(&format!("  # note: {}\n", note));
+            }
+            let script = runner::strip_sudo_for_root_user(&self.item_commands(item));
+            let mut lines: Vec<&str> = script.lines().collect();
+            match lines.as_slice() {
+                // String entries run through sh.
+                [line] if !line.contains("TARGET_USER") => entry.push_str(&format!("  - {}\n", quote(line))),
+                // Multi-line steps may contain shell control flow; steps for the invoking user apply to root.
+                _ => {
+                    if script.contains("TARGET_USER") {
+                        lines.insert(0, "TARGET_USER=root");
+                    }
+                    entry.push_str(&format!("  - [bash, -ec, {}]\n", quote(&lines.join("\n"))));
+                }
+            }
+            commands.push(entry);
+        }
+
+        if !commands.is_empty() {
+            text.push_str("runcmd:\n");
+            text.push_str(&commands.concat());
+        }
+        if reboot {
+            text.push_str("power_state:\n  mode: reboot\n  message: \"el-init: rebooting to finish setup\"\n  condition: true\n");
+        }
+        text
+    }
+
+    /// The step that backs up the config files these items modify, or nothing when backups
+    /// are off. Each file is copied to `<file>.el-init.bak`, keeping older backups numbered.
+    fn config_backups<'a>(&self, items: impl Iterator<Item = &'a SelectedItem>) -> String {
+        let mut files: Vec<&str> = Vec::new();
+        for item in items.filter(|item| !self.already_enabled(item)) {
+            for file in item.touches_config {
+                if !files.contains(file) {
+                    files.push(file);
+                }
+            }
+        }
+        if !self.script_ctx.backup_configs || files.is_empty() {
+            return String::new();
+        }
+        let root_note = if self.no_sudo { "# NOTE: this step requires root\n" } else { "" };
+        format!(r#"{}print_step "Backing up config files (<file>.el-init.bak)"
+for file in {}; do
+    if [ -e "$file" ]; then sudo cp --backup=numbered "$file" "$file.el-init.bak"; fi
+done
+"#, root_note, files.join(" "))
+    }
+
+    /// Whether any of these items runs config-manager or enables a COPR.
+    fn needs_plugins<'a>(&self, mut items: impl Iterator<Item = &'a SelectedItem>) -> bool {
+        items.any(|item| {
+            let script = self.item_commands(item);
+            script.contains("config-manager") || script.contains("copr enable")
+        })
+    }
+
+    /// An item's commands with its version pins, group excludes and install flags applied.
+    fn item_commands(&self, item: &SelectedItem) -> String {
+        let mut script = (item.script_fn)(&self.script_ctx);
+        if let Some(pin) = self.version_pins.get(item.id) {
+            script = pin.apply(&script);
+        }
+        // A chosen subset leaves no group install for the excludes to apply to.
+        if let Some(members) = self.group_members.get(item.id) {
+            script = dnf::with_group_members(&script, members);
+        }
+        if let Some(excludes) = self.group_excludes.get(item.id) {
+            script = dnf::with_group_excludes(&script, excludes);
+        }
+        // yum and microdnf have neither flag.
+        if self.script_ctx.package_manager != PackageManager::Dnf {
+            return script;
+        }
+        let flags: Vec<&str> = [(self.allow_erasing, "--allowerasing"), (self.nobest, "--nobest")].into_iter()
+            .filter_map(|(on, flag)| on.then_some(flag))
+            .collect();
+        dnf::with_install_flags(&script, &flags)
+    }
+
+    /// Appends a single item's step to the script, applying the no-sudo rewrite when enabled.
+    fn push_item_script(&self, command_text: &mut String, item: &SelectedItem) {
+        if self.already_enabled(item) {
+            command_text.push_str(&format!("# {}: skipped, repository already enabled\n", item.name));
+            return;
+        }
+        if item.auto_added {
+            command_text.push_str(&format!("# + auto-added: {}\n", item.qualified_name));
+        }
+        if let Some(note) = &item.note {
+            command_text.push_str(&format!("# note: {}\n", note));
+        }
+        if item.requires.iter().any(|id| self.no_auto_include.contains(id)) {
+            let mut selected = HashSet::new();
+            self.menu_tree.borrow().selected_ids(&mut selected);
+            for id in item.requires.iter().filter(|id| self.no_auto_include.contains(*id) && !selected.contains(*id)) {
+                command_text.push_str(&format!("# NOTE: assumes {} is already installed\n", id));
+            }
+        }
+        let script = self.item_commands(item);
+        if !self.no_sudo {
+            command_text.push_str(&format!("print_step \"{}\"\n", item.name));
+            command_text.push_str(&script);
+            command_text.push('\n');
+            return;
+        }
+
+        let lines: Vec<String> = script.lines().map(|line| to_user_scope(line).unwrap_or_else(|| line.to_string())).collect();
+        if lines.iter().any(|line| line.starts_with("sudo ")) {
+            command_text.push_str("# NOTE: this step requires root\n");
+        }
+        command_text.push_str(&format!("print_step \"{}\"\n", item.name));
+        command_text.push_str(&lines.join("\n"));
+        command_text.push('\n');
+    }
+
+    /// Renders the step a single item contributes to the script.
+    fn item_script(&self, item: &SelectedItem) -> String {
+        let mut text = String::new();
+        self.push_item_script(&mut text, item);
+        text
+    }
+
+    /// Looks up the members of a group-install item and shows them in the info popup.
+    fn show_group_info(&mut self, script_fn: ScriptFn) {
+        match dnf::group_name(&script_fn(&self.script_ctx)) {
+            Some(group) => self.start_group_lookup(group, None),
+            None => self.info_popup = Some(("Group Info".to_string(), "This item does not install a package group.".to_string())),
+        }
+    }
+
+    /// Looks up the members of a group-install item and opens the picker for choosing a subset.
+    fn expand_group(&mut self, id: &'static str, script_fn: ScriptFn) {
+        match dnf::group_name(&script_fn(&self.script_ctx)) {
+            Some(group) => self.start_group_lookup(group, Some(id)),
+            None => self.info_popup = Some(("Expand Group".to_string(), "This item does not install a package group.".to_string())),
+        }
+    }
+
+    /// Runs `dnf group info` on a background thread; loading the metadata can take a while and
+    /// the menu keeps drawing meanwhile. `tick` hands the result to `finish_group_lookup`.
+    fn start_group_lookup(&mut self, group: String, expand: Option<&'static str>) {
+        let (sender, result) = mpsc::channel();
+        let proxy = self.script_ctx.proxy.clone();
+        let name = group.clone();
+        thread::spawn(move || {
+            // The receiver is gone when the lookup was cancelled.
+            let _ = sender.send(dnf::group_info(&name, proxy.as_deref()));
+        });
+        self.group_lookup = Some(GroupLookup { group, expand, result });
+    }
+
+    fn finish_group_lookup(&mut self, lookup: GroupLookup, result: Result<Vec<GroupSection>, String>) {
+        let GroupLookup { group, expand, .. } = lookup;
+        match (expand, result) {
+            (Some(id), Ok(sections)) => {
+                self.member_picker = Some(MemberPicker::new(id, &group, &sections, self.group_members.get(id)));
+                self.state = AppState::Expanding;
+            }
+            (Some(_), Err(err)) => self.info_popup = Some((format!("Expand Group: {}", group), err)),
+            (None, result) => {
+                let body = match result {
+                    Ok(sections) => sections.iter()
+                        .map(|section| format!("{}:\n  {}", section.title, section.members.join("\n  ")))
+                        .collect::<Vec<_>>()
+                        .join("\n\n"),
+                    Err(err) => err,
+                };
+                self.info_popup = Some((format!("Group Info: {}", group), body));
+            }
+        }
+    }
+
+    /// Dumps everything needed to reproduce the current script as JSON, for pasting into bug reports.
+    fn repro_report(&self) -> String {
+        let mut ids = HashSet::new();
+        let mut notes = HashMap::new();
+        let tree = self.menu_tree.borrow();
+        tree.selected_ids(&mut ids);
+        tree.notes(&mut notes);
+        let mut items: Vec<_> = ids.into_iter().collect();
+        items.sort_unstable();
+        let notes: BTreeMap<_, _> = notes.into_iter().collect();
+        let pins: BTreeMap<_, _> = self.version_pins.iter()
+            .map(|(id, pin)| (*id, serde_json::json!({ "packages": pin.specs(), "lock": pin.lock })))
+            .collect();
+        let report = serde_json::json!({
+            "el_init_version": env!("CARGO_PKG_VERSION"),
+            "detected_os": format!("{:?}", self.detected_os),
+            "os_version": self.os_version,
+            "target": format!("{:?}", self.os_distro),
+            "state": format!("{:?}", self.state),
+            "nav_path": self.nav_path.iter().map(|node| node.borrow().name().to_string()).collect::<Vec<_>>(),
+            "selected_index": self.selected_index,
+            "active_tab": self.active_tab,
+            "category_filter": self.category_filter.map(|filter| format!("{:?}", filter)),
+            "tag_filter": self.tag_filter,
+            "flat_view": self.flat_view,
+            "items": items,
+            "notes": notes,
+            "version_pins": pins,
+            "no_auto_include": self.no_auto_include.iter().collect::<BTreeSet<_>>(),
+            "group_excludes": self.group_excludes.iter().collect::<BTreeMap<_, _>>(),
+            "group_members": self.group_members.iter().collect::<BTreeMap<_, _>>(),
+            "no_sudo": self.no_sudo,
+            "reboot_requested": self.reboot_requested,
+            "reboot_at": self.reboot_at,
+            "over_ssh": self.over_ssh,
+            "privilege": format!("{:?}", self.privilege),
+            "arch": format!("{:?}", self.arch),
+            "output_format": self.output_format.label(),
+            "settings": {
+                "epel_version": self.script_ctx.epel_version,
+                "flathub_url": self.script_ctx.flathub_url,
+                "parallel_downloads": self.script_ctx.parallel_downloads,
+                "proxy": self.script_ctx.proxy.is_some(),
+                "kvm_group_add": self.script_ctx.kvm_group_add,
+                "backup_configs": self.script_ctx.backup_configs,
+                "record_run": self.script_ctx.record_run,
+                "allow_erasing": self.allow_erasing,
+                "nobest": self.nobest,
+                "dnf_flavor": format!("{:?}", self.script_ctx.dnf_flavor),
+                "package_manager": self.script_ctx.package_manager.binary(),
+            },
+        });
+        serde_json::to_string_pretty(&report).unwrap_or_default()
+    }
+
+    fn glyphs(&self) -> &'static TreeGlyphs {
+        if self.ascii { &ASCII_GLYPHS } else { &UNICODE_GLYPHS }
+    }
+
+    /// Names of selected items that need a reboot to take effect.
+    fn reboot_reasons(&self) -> Vec<String> {
+        let mut items = Vec::new();
+        self.menu_tree.borrow().get_selected_items_info(&mut items);
+        items.into_iter().filter(|i| i.needs_reboot).map(|i| i.qualified_name).collect()
+    }
+
+    fn exit_summary(&self) -> ExitSummary {
+        let (repos, packages) = self.selection_counts();
+        ExitSummary { detected: self.detected_os, selected: repos + packages, saved: self.saved_paths.clone() }
+    }
+
+    /// Selected items whose script is only a placeholder.
+    fn selected_stubs(&self) -> Vec<String> {
+        let mut items = Vec::new();
+        self.menu_tree.borrow().get_selected_items_info(&mut items);
+        items.into_iter().filter(|i| i.is_stub).map(|i| i.qualified_name).collect()
+    }
+
+    /// Gets just the names of selected items for display in the UI.
+    fn get_selected_items(&self) -> Vec<String> {
+        let mut items_info = Vec::new();
+        self.menu_tree.borrow().get_selected_items_info(&mut items_info);
+        items_info.iter().map(|i| {
+            if self.already_enabled(i) { format!("{} (already enabled)", i.name) } else { i.name.clone() }
+        }).collect()
+    }
+}
+
+/// Warns about repos enabled with config-manager on RHEL, where subscription-manager owns the
+/// repo definitions and a second definition of the same repo conflicts with it.
+fn push_rhel_repo_warnings(command_text: &mut String, script: &str) {
+    for line in script.lines() {
+        if let Some(repo) = dnf::config_manager_repo(line) {
+            command_text.push_str(&format!(
+                "# WARNING: '{}' is not a subscription-manager repo; enabling it with config-manager may conflict on RHEL.\n",
+                repo,
+            ));
+        }
+    }
+}
+
+/// Writes one JSON line per step to the invoking user's `last-run.json` (see `last_run`). A step
+/// ends at the next `print_step` with exit code 0, or at exit with the script's status, which
+/// under `set -e` is the failing command's.
+const RUN_LOG_SECTION: &str = r#"# --- RUN LOG ---
+RUN_LOG="$(getent passwd "$TARGET_USER" | cut -d: -f6)/.local/share/el-init/last-run.json"
+mkdir -p "$(dirname "$RUN_LOG")"
+: > "$RUN_LOG"
+STEP_NAME=""
+STEP_START=$SECONDS
+record_step() {
+    [ -n "$STEP_NAME" ] || return 0
+    local name="${STEP_NAME//\\/\\\\}"
+    name="${name//\"/\\\"}"
+    printf '{"step": "%s", "exit_code": %d, "seconds": %d}\n' "$name" "$1" "$((SECONDS - STEP_START))" >> "$RUN_LOG"
+    STEP_NAME=""
+}
+# Run as root, the log still belongs to the user who reads it back.
+trap 'record_step $?; chown -R "$TARGET_USER" "$(dirname "$RUN_LOG")" 2> /dev/null || true' EXIT
+
+"#;
+
+/// Exports the proxy for this script, passes it through sudo, and sets it in dnf.conf so later
+/// dnf runs use it too.
+fn proxy_section(proxy: &str) -> String {
+    let vars = ["http_proxy", "https_proxy", "HTTP_PROXY", "HTTPS_PROXY"];
+    let mut section = String::from("# --- PROXY ---\n");
+    let exports: Vec<String> = vars.iter().map(|var| format!("{}='{}'", var, proxy)).collect();
+    section.push_str(&format!("export {}\n", exports.join(" ")));
+    section.push_str("# sudo resets the environment, so the proxy variables are passed on explicitly.\n");
+    section.push_str(&format!("sudo() {{ command sudo --preserve-env={} \"$@\"; }}\n", vars.join(",")));
+    section.push_str("print_step \"Setting the dnf proxy (backup: /etc/dnf/dnf.conf.el-init.bak)\"\n");
+    section.push_str("sudo cp -n /etc/dnf/dnf.conf /etc/dnf/dnf.conf.el-init.bak\n");
+    section.push_str("sudo sed -i '/^proxy=/d' /etc/dnf/dnf.conf\n");
+    section.push_str(&format!("echo 'proxy={}' | sudo tee -a /etc/dnf/dnf.conf > /dev/null\n\n", proxy));
+    section
+}
+
+/// Drops command lines of `step` that an earlier step already ran, recording the rest in `seen`.
+/// Comments, `print_step` labels and anything inside a compound command or a continued line
+/// are kept, so removing a line never changes the script's structure.
+fn dedupe_commands(step: &str, seen: &mut HashSet<String>) -> String {
+    let mut kept = String::new();
+    let mut depth = 0usize;
+    let mut continued = false;
+    for line in step.lines() {
+        let trimmed = line.trim();
+        let word = trimmed.split_whitespace().next().unwrap_or("");
+        let top_level = depth == 0 && !continued;
+        let one_liner = ["fi", "done", "esac"].iter().any(|end| trimmed.ends_with(&format!("; {}", end)));
+        match word {
+            "if" | "for" | "while" | "until" | "case" if !one_liner => depth += 1,
+            "fi" | "done" | "esac" => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        let is_command = !trimmed.is_empty() && !trimmed.starts_with('#') && word != "print_step";
+        let simple = top_level && depth == 0 && !trimmed.ends_with('\\');
+        continued = trimmed.ends_with('\\');
+        if is_command && simple && !seen.insert(trimmed.to_string()) {
+            continue;
+        }
+        kept.push_str(line);
+        kept.push('\n');
+    }
+    kept
+}
+
+/// Rewrites a user-scoped command (currently flatpak) to run without sudo.
+/// Returns `None` for commands that still need root.
+fn to_user_scope(line: &str) -> Option<String> {
+    let command = line.strip_prefix("sudo ").unwrap_or(line);
+    let rest = command.strip_prefix("flatpak ")?;
+    for sub in ["install", "remote-add", "uninstall", "update"] {
+        if let Some(args) = rest.strip_prefix(sub) {
+            return Some(format!("flatpak {} --user{}", sub, args));
+        }
+    }
+    Some(command.to_string())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = match cli::parse_args() {
+        Ok(cli::CliCommand::Run(args)) => args,
+        Ok(cli::CliCommand::CheckDistro { json }) => check_distro(json),
+        Ok(cli::CliCommand::DiffProfiles { a, b }) => diff_profiles(&a, &b),
+        Ok(cli::CliCommand::PrintHelp) => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Err(err) => {
+            eprintln!("el-init: {}\n\n{}", err, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if args.stdin_profile {
+        return run_headless(&args);
+    }
+    if !io::stdin().is_terminal() {
+        eprintln!("el-init: stdin is not a terminal; use --stdin to read a profile from it");
+        std::process::exit(2);
+    }
+
+    let log = match &args.log_file {
+        Some(path) => match SessionLog::create(path) {
+            Ok(log) => Some(log),
+            Err(err) => {
+                eprintln!("el-init: cannot open log file {}: {}", path.display(), err);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+
+    recovery::install_panic_hook();
+    let (mut terminal, fallback) = setup_terminal()?;
+    let mut app = App::new(&args);
+    app.log = log;
+    app.crash_recovery = true;
+    app.last_run = last_run::load();
+    if let Some(failed) = app.last_run.iter().flatten().find(|step| step.exit_code != 0) {
+        let body = format!("The last run failed at \"{}\". Press L in the menu for the breakdown.", failed.step);
+        app.push_info("Last Run", body);
+    }
+    if let Some(profile) = recovery::load() {
+        app.recovered = Some(profile);
+        // Restoring only changes the selection, so there is never an action to take here.
+        app.confirm(Confirmation::RestoreSession);
+    }
+    if let Some(err) = &fallback {
+        log_event!(app, "alternate screen unavailable: {}", err);
+        let message = format!("The alternate screen is not available ({}), so the menu is drawn inline.", err);
+        app.push_info("Terminal", message);
+    }
+    let res = run_app(&mut terminal, app);
+
+    disable_raw_mode()?;
+    if fallback.is_none() {
+        execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
+    } else {
+        terminal.clear()?;
+    }
+    terminal.show_cursor()?;
+    if res.is_ok() {
+        recovery::clear();
+    }
+
+    match res {
+        Ok(ActionAfterExit::RunScript { script, saved_path, two_phase }) => match &args.deploy {
+            Some(host) => runner::deploy(&script, host)?,
+            None => runner::run(&script, saved_path, two_phase, args.assume_yes)?,
+        },
+        Ok(ActionAfterExit::InstallFirstboot { script }) => runner::install_firstboot(&script)?,
+        Ok(ActionAfterExit::InstallTimer { script, schedule }) => runner::install_timer(&script, &schedule)?,
+        Ok(ActionAfterExit::Quit(summary)) => print!("{}", summary),
+        Err(err) => {
+            // Only terminal-backend failures reach this point; everything else is shown in the TUI.
+            eprintln!("el-init: terminal error: {}", err);
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+/// Enters raw mode and the alternate screen with mouse capture. Consoles that reject the
+/// alternate screen or mouse capture get an inline viewport instead; the error is returned
+/// alongside the terminal so the caller can tell the user.
+fn setup_terminal() -> io::Result<(Terminal<CrosstermBackend<io::Stdout>>, Option<io::Error>)> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    match execute!(stdout, EnterAlternateScreen, EnableMouseCapture) {
+        Ok(()) => Ok((Terminal::new(CrosstermBackend::new(stdout))?, None)),
+        Err(err) => {
+            // Undo whichever half succeeded before drawing in the normal screen.
+            let _ = execute!(stdout, DisableMouseCapture, LeaveAlternateScreen);
+            let height = crossterm::terminal::size().map_or(24, |(_, rows)| rows);
+            let options = TerminalOptions { viewport: Viewport::Inline(height) };
+            Ok((Terminal::with_options(CrosstermBackend::new(stdout), options)?, Some(err)))
+        }
+    }
+}
+
+/// Applies a profile read from stdin and prints or runs its script, without the TUI.
+fn run_headless(args: &cli::CliArgs) -> Result<(), Box<dyn Error>> {
+    let mut text = String::new();
+    io::stdin().read_to_string(&mut text)?;
+    let mut app = App::new(args);
+    if let Err(err) = Profile::from_json(&text).and_then(|profile| app.apply_profile(&profile)) {
+        eprintln!("el-init: {}", err);
+        std::process::exit(2);
+    }
+
+    let script = app.script(app.reboot_requested);
+    if !args.run {
+        print!("{}", script);
+        return Ok(());
+    }
+    match &args.deploy {
+        Some(host) => runner::deploy(&script, host)?,
+        // stdin held the profile, so there is nothing left to answer the prompt with.
+        None => runner::run(&script, None, false, true)?,
+    }
+    Ok(())
+}
+
+/// Consecutive input errors tolerated before giving up on the terminal.
+const MAX_READ_FAILURES: u32 = 3;
+
+/// Leaves raw mode and the alternate screen while `f` runs, e.g. to hand the terminal to an
+/// editor, then restores both and forces a full redraw.
+fn with_terminal_released<B: Backend, T>(terminal: &mut Terminal<B>, f: impl FnOnce() -> T) -> io::Result<T> {
+    disable_raw_mode()?;
+    // These fail in the inline fallback, where there is no alternate screen to leave.
+    let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture);
+    let result = f();
+    enable_raw_mode()?;
+    let _ = execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture);
+    terminal.clear()?;
+    Ok(result)
+}
+
+/// How long `run_app` waits for input before redrawing.
+const TICK_RATE: Duration = Duration::from_millis(250);
+/// How long a save result stays on screen.
+const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(3);
+/// How long the selection must stay unchanged before it is autosaved for crash recovery.
+const RECOVERY_DEBOUNCE: Duration = Duration::from_secs(1);
+
+fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<ActionAfterExit> {
+    let mut read_failures = 0;
+    let mut logged_state = app.state.clone();
+    log_event!(app, "session start: {:?}, state {:?}", app.os_distro, logged_state);
+    loop {
+        if app.log.is_some() && app.state != logged_state {
+            log_event!(app, "state {:?} -> {:?}", logged_state, app.state);
+            logged_state = app.state.clone();
+        }
+        app.tick();
+        terminal.draw(|f| ui(f, &mut app))?;
+
+        // Poll instead of blocking so timed UI such as the status message updates without input.
+        let polled = event::poll(TICK_RATE).and_then(|ready| if ready { event::read().map(Some) } else { Ok(None) });
+        let event = match polled {
+            Ok(Some(event)) => {
+                read_failures = 0;
+                event
+            }
+            Ok(None) => continue,
+            Err(err) => {
+                read_failures += 1;
+                if read_failures >= MAX_READ_FAILURES {
+                    return Err(err);
+                }
+                app.show_error(format!("Failed to read terminal input: {}", err));
+                continue;
+            }
+        };
+
+        if let Event::Key(key) = event {
+            log_event!(app, "key {:?} {:?}, index {}", key.code, key.modifiers, app.selected_index);
+            if app.detail_popup.is_some() {
+                match key.code {
+                    KeyCode::Up => app.detail_scroll = app.detail_scroll.saturating_sub(1),
+                    KeyCode::Down => app.detail_scroll = app.detail_scroll.saturating_add(1),
+                    KeyCode::PageUp => app.detail_scroll = app.detail_scroll.saturating_sub(10),
+                    KeyCode::PageDown => app.detail_scroll = app.detail_scroll.saturating_add(10),
+                    _ => app.detail_popup = None,
+                }
+                continue;
+            }
+            if app.group_lookup.is_some() {
+                if key.code == KeyCode::Esc {
+                    app.group_lookup = None;
+                }
+                continue;
+            }
+            if app.show_help || app.info_popup.is_some() {
+                app.show_help = false;
+                app.info_popup = None;
+                continue;
+            }
+            let typing = matches!(app.state, AppState::Saving | AppState::Settings | AppState::Pinning | AppState::Excluding | AppState::Noting | AppState::Profiles | AppState::Scheduling | AppState::Timer | AppState::Searching);
+            if key.code == KeyCode::Char('?') && !typing {
+                app.show_help = true;
+                continue;
+            }
+            // Undocumented: Ctrl-D shows the state dump that bug reports should include.
+            if key.code == KeyCode::Char('d') && key.modifiers.contains(KeyModifiers::CONTROL) && !typing {
+                let report = app.repro_report();
+                log_event!(app, "repro report\n{}", report);
+                app.info_popup = Some(("Bug Report State (select and copy)".to_string(), report));
+                continue;
+            }
+
+            match app.state {
+                AppState::Running => {
+                    let visible_nodes = get_visible_nodes(&app);
+                    let visible_len = visible_nodes.len();
+
+                    if visible_len > 0 {
                         app.selected_index = app.selected_index.min(visible_len - 1);
                     } else {
                         app.selected_index = 0;
                     }
 
                     match key.code {
-                        KeyCode::Char('q') => return Ok(ActionAfterExit::Quit),
+                        KeyCode::Char('q') => {
+                            if app.get_selected_items().is_empty() {
+                                return Ok(ActionAfterExit::Quit(app.exit_summary()));
+                            }
+                            if let Some(action) = app.confirm(Confirmation::Quit) {
+                                return Ok(action);
+                            }
+                        }
                         KeyCode::Char('i') => { app.state = AppState::Finished; app.reboot_requested = false; },
                         KeyCode::Char('r') => { app.state = AppState::Finished; app.reboot_requested = true; },
-                        KeyCode::Down => {
-                            if !visible_nodes.is_empty() {
-                                app.selected_index = (app.selected_index + 1) % visible_nodes.len();
+                        KeyCode::Char('u') => {
+                            app.no_sudo = !app.no_sudo;
+                            app.invalidate_script();
+                        }
+                        KeyCode::Char('w') => app.wrap_navigation = !app.wrap_navigation,
+                        KeyCode::Char('G') => {
+                            let item = visible_nodes.get(app.selected_index).and_then(|(_, node)| match &*node.borrow() {
+                                MenuNode::Item { id, script_fn, .. } => Some((*id, *script_fn)),
+                                _ => None,
+                            });
+                            if let Some((id, script_fn)) = item {
+                                app.expand_group(id, script_fn);
                             }
                         }
-                        KeyCode::Up => {
-                            if !visible_nodes.is_empty() {
-                                app.selected_index = (app.selected_index + visible_nodes.len() - 1) % visible_nodes.len();
+                        KeyCode::Char('y') => {
+                            let item = visible_nodes.get(app.selected_index).and_then(|(_, node)| match &*node.borrow() {
+                                MenuNode::Item { name, script_fn, .. } => Some((name.clone(), *script_fn)),
+                                _ => None,
+                            });
+                            if let Some((name, script_fn)) = item {
+                                let message = match clipboard::copy(&script_fn(&app.script_ctx)) {
+                                    Ok(target) => format!("Copied the command for {} ({})", name, target),
+                                    Err(err) => format!("Could not copy the command for {}: {}", name, err),
+                                };
+                                app.set_status(message);
                             }
                         }
-                        KeyCode::Right | KeyCode::Enter => {
-                            if let Some((_, selected_rc)) = visible_nodes.get(app.selected_index) {
-                                let mut node_mut = selected_rc.borrow_mut();
-                                match &mut *node_mut {
-                                    MenuNode::Menu { .. } => {
-                                        drop(node_mut);
-                                        app.nav_path.push(selected_rc.clone());
-                                        app.selected_index = 0;
-                                    }
-                                    MenuNode::Item { selected, .. } => {
-                                        *selected = !*selected;
+                        KeyCode::Char('L') => {
+                            let body = match &app.last_run {
+                                Some(steps) => last_run::report(steps),
+                                None => format!("No run log at ~/{}. Turn on run recording in settings [o] \
+                                    and run a generated script.", last_run::LOG_PATH),
+                            };
+                            app.info_popup = Some(("Last Run".to_string(), body));
+                        }
+                        KeyCode::Char('g') => {
+                            let script_fn = visible_nodes.get(app.selected_index).and_then(|(_, node)| match &*node.borrow() {
+                                MenuNode::Item { script_fn, .. } => Some(*script_fn),
+                                _ => None,
+                            });
+                            if let Some(script_fn) = script_fn {
+                                app.show_group_info(script_fn);
+                            }
+                        }
+                        KeyCode::Char('t') => {
+                            // Only rows shown right now; menus are left alone rather than recursed into.
+                            for (_, node) in &visible_nodes {
+                                if matches!(&*node.borrow(), MenuNode::Item { .. }) {
+                                    app.toggle_item(node);
+                                }
+                            }
+                        }
+                        KeyCode::Char('x') => {
+                            app.invert_selection(&visible_nodes);
+                            app.selected_index = app.selected_index.min(visible_len.saturating_sub(1));
+                        }
+                        // `n` already jumps between items, so clearing a submenu is on `c`.
+                        KeyCode::Char(key @ ('a' | 'c')) => {
+                            if let Some((_, node)) = visible_nodes.get(app.selected_index)
+                                && !matches!(&*node.borrow(), MenuNode::Item { .. })
+                            {
+                                app.set_subtree_selection(node, key == 'a');
+                            }
+                        }
+                        KeyCode::Char('m') => {
+                            if let Some((_, node)) = visible_nodes.get(app.selected_index)
+                                && let MenuNode::Item { note, .. } = &*node.borrow()
+                            {
+                                app.note_input = TextInput::new(note.clone().unwrap_or_default());
+                                app.note_target = Some(node.clone());
+                                app.state = AppState::Noting;
+                            }
+                        }
+                        KeyCode::Char('A') => {
+                            if let Some((_, node)) = visible_nodes.get(app.selected_index)
+                                && let MenuNode::Item { id, .. } = &*node.borrow()
+                            {
+                                if !app.no_auto_include.insert(id) {
+                                    app.no_auto_include.remove(id);
+                                }
+                                app.invalidate_script();
+                            }
+                        }
+                        KeyCode::Char('I') if app.debug => app.show_ids = !app.show_ids,
+                        KeyCode::Char('s') => {
+                            app.recent_first = !app.recent_first;
+                            app.selected_index = 0;
+                        }
+                        KeyCode::Char('v') => {
+                            app.flat_view = !app.flat_view;
+                            app.selected_index = 0;
+                        }
+                        KeyCode::Char('/') => {
+                            app.search_input.clear();
+                            app.state = AppState::Searching;
+                        }
+                        KeyCode::Char('1') => app.set_category_filter(Some(ScriptCategory::Repository)),
+                        KeyCode::Char('2') => app.set_category_filter(Some(ScriptCategory::General)),
+                        KeyCode::Char('0') => {
+                            app.set_category_filter(None);
+                            app.tag_filter.clear();
+                        }
+                        KeyCode::Char('T') => {
+                            app.tag_index = 0;
+                            app.state = AppState::Tagging;
+                        }
+                        KeyCode::Char('D') => app.rebuild_for(app.os_distro.next_target()),
+                        KeyCode::Char('o') => {
+                            app.settings_form = Some(SettingsForm::from_context(&app.script_ctx, app.syntax_highlight, app.allow_erasing, app.nobest));
+                            app.state = AppState::Settings;
+                        }
+                        KeyCode::Char('d') => {
+                            let item = visible_nodes.get(app.selected_index).and_then(|(_, node)| match &*node.borrow() {
+                                MenuNode::Item { id, name, .. } => Some((*id, name.clone())),
+                                _ => None,
+                            });
+                            if let Some((id, name)) = item {
+                                match scripts::repo_details(id) {
+                                    Some(details) => {
+                                        app.detail_popup = Some((name, details));
+                                        app.detail_scroll = 0;
                                     }
+                                    None => app.info_popup = Some((name, "No repository details for this item.".to_string())),
                                 }
                             }
                         }
-                        KeyCode::Left | KeyCode::Backspace => {
-                            if app.nav_path.len() > 1 {
-                                app.nav_path.pop();
-                                app.selected_index = 0;
+                        KeyCode::Char('p') => app.show_preview = !app.show_preview,
+                        KeyCode::Char('W') => {
+                            app.enabled_repos = dnf::enabled_repos().unwrap_or_default();
+                            app.change_report = app.change_report(dnf::installed_packages);
+                            app.report_scroll = 0;
+                            app.state = AppState::Report;
+                        }
+                        KeyCode::Char('P') => {
+                            app.profile_manager = Some(ProfileManager::open());
+                            app.state = AppState::Profiles;
+                        }
+                        KeyCode::Down if !visible_nodes.is_empty() => {
+                            app.selected_index = if app.wrap_navigation {
+                                (app.selected_index + 1) % visible_len
+                            } else {
+                                (app.selected_index + 1).min(visible_len - 1)
+                            };
+                        }
+                        KeyCode::Tab | KeyCode::BackTab => {
+                            if let Some(index) = next_section_index(&visible_nodes, app.selected_index, key.code == KeyCode::Tab, app.glyphs()) {
+                                app.selected_index = index;
+                            }
+                        }
+                        KeyCode::Char(key @ ('n' | 'N')) => {
+                            if let Some(index) = next_item_index(&visible_nodes, app.selected_index, key == 'n', app.wrap_navigation) {
+                                app.selected_index = index;
+                            }
+                        }
+                        KeyCode::Up if !visible_nodes.is_empty() => {
+                            app.selected_index = if app.wrap_navigation {
+                                (app.selected_index + visible_len - 1) % visible_len
+                            } else {
+                                app.selected_index.saturating_sub(1)
+                            };
+                        }
+                        KeyCode::Left | KeyCode::Backspace if app.category_filter.is_some() => app.set_category_filter(None),
+                        KeyCode::Left | KeyCode::Backspace if !app.tag_filter.is_empty() => {
+                            app.tag_filter.clear();
+                            app.selected_index = 0;
+                        }
+                        KeyCode::Left if app.nav_path.len() == 1 => app.switch_tab(-1),
+                        KeyCode::Right if app.nav_path.len() == 1 => app.switch_tab(1),
+                        KeyCode::Right | KeyCode::Enter => {
+                            if let Some((_, selected_rc)) = visible_nodes.get(app.selected_index) {
+                                let is_item = matches!(&*selected_rc.borrow(), MenuNode::Item { .. });
+                                let is_empty = selected_rc.borrow().children().is_some_and(|children| children.is_empty());
+                                let unavailable = match &*selected_rc.borrow() {
+                                    MenuNode::Item { name, availability: Availability::Unavailable(reason), .. } => Some((name.clone(), *reason)),
+                                    _ => None,
+                                };
+                                if let Some((name, reason)) = unavailable {
+                                    app.info_popup = Some((name, format!("This item cannot be selected: {}.", reason)));
+                                } else if is_item {
+                                    app.toggle_item(selected_rc);
+                                } else if is_empty {
+                                    let name = selected_rc.borrow().name().to_string();
+                                    app.info_popup = Some((name, "This menu has no items yet.".to_string()));
+                                } else {
+                                    app.descend(selected_rc.clone());
+                                }
                             }
                         }
+                        KeyCode::Left | KeyCode::Backspace => app.go_back(),
+                        KeyCode::Char('f') => app.go_forward(),
                         _ => {}
                     }
                 },
                 AppState::Finished => match key.code {
-                    KeyCode::Char('q') => return Ok(ActionAfterExit::Quit),
-                    KeyCode::Char('s') => app.state = AppState::Saving,
-                    KeyCode::Char('r') => return Ok(ActionAfterExit::RunScript(app.generate_commands(app.reboot_requested))),
+                    KeyCode::Down => app.finished_index += 1,
+                    KeyCode::Up => app.finished_index = app.finished_index.saturating_sub(1),
+                    KeyCode::Tab => app.show_full_script = !app.show_full_script,
+                    KeyCode::Char('t') => app.two_phase = !app.two_phase,
+                    // The script cache is keyed by the reboot flag, so nothing needs invalidating.
+                    KeyCode::Char('b') => app.reboot_requested = !app.reboot_requested,
+                    KeyCode::Char('B') => {
+                        app.reboot_at_input = TextInput::new(app.reboot_at.clone().unwrap_or_default());
+                        app.state = AppState::Scheduling;
+                    }
+                    KeyCode::Char('p') => match app.profile_path.clone() {
+                        Some(path) => {
+                            let mut selected = Vec::new();
+                            app.menu_tree.borrow().collect_items(None, &mut selected, false);
+                            let profile = TomlProfile { items: selected.into_iter().map(|item| item.qualified_name).collect() };
+                            match profile.write(&path) {
+                                Ok(()) => app.set_status(format!("Saved the selection to {}", path.display())),
+                                Err(err) => app.show_error(err),
+                            }
+                        }
+                        None => app.info_popup = Some(("Save Profile".to_string(), "Start with --profile PATH to save the selection to a file.".to_string())),
+                    },
+                    KeyCode::Char('o') => {
+                        app.output_format = app.output_format.next();
+                        app.invalidate_script();
+                    }
+                    KeyCode::Char('c') if !app.output_format.is_bash() => {
+                        let message = format!("Only bash scripts can be checked with bash -n, not a {}.", app.output_format.label());
+                        app.info_popup = Some(("Output Format".to_string(), message));
+                    }
+                    KeyCode::Char('e' | 'E') if app.output_format == OutputFormat::Bundle => {
+                        let message = "Running the repository section and editing work on the single-distro bash script. Switch to it with [o].";
+                        app.info_popup = Some(("Output Format".to_string(), message.to_string()));
+                    }
+                    KeyCode::Char('e' | 'E' | 'f' | 'r' | 'S' | 'x') if !app.output_format.is_bash() => {
+                        let message = format!("Only bash scripts can be run. Save the {} with [s] instead.", app.output_format.label());
+                        app.info_popup = Some(("Output Format".to_string(), message));
+                    }
+                    // The reboot block is left out: rebooting from the unit would run it again.
+                    KeyCode::Char('f') => return Ok(ActionAfterExit::InstallFirstboot { script: app.script(false) }),
+                    KeyCode::Char('S') => app.state = AppState::Timer,
+                    KeyCode::Char('q') => {
+                        if let Some(action) = app.confirm(Confirmation::Quit) {
+                            return Ok(action);
+                        }
+                    }
+                    KeyCode::Char('s') => { app.save_and_run = false; app.state = AppState::Saving; },
+                    KeyCode::Char('x') => { app.save_and_run = true; app.state = AppState::Saving; },
+                    KeyCode::Char('r') if app.reboot_requested => {
+                        app.run_saved_path = None;
+                        if let Some(action) = app.confirm(Confirmation::RunWithReboot) {
+                            return Ok(action);
+                        }
+                    }
+                    KeyCode::Char('r') => return Ok(ActionAfterExit::RunScript { script: app.script(false), saved_path: None, two_phase: app.two_phase }),
+                    KeyCode::Char('c') => {
+                        let body = match runner::syntax_check(&app.script(app.reboot_requested)) {
+                            Ok(errors) if errors.is_empty() => "bash -n: syntax OK".to_string(),
+                            Ok(errors) => errors.join("\n"),
+                            Err(err) => err,
+                        };
+                        app.info_popup = Some(("Syntax Check".to_string(), body));
+                    }
+                    KeyCode::Char('E') => {
+                        let script = app.script(app.reboot_requested);
+                        match with_terminal_released(terminal, || runner::edit(&script))? {
+                            Ok(Some(edited)) => {
+                                log_event!(app, "script edited: {} bytes", edited.len());
+                                app.edited_script = Some((app.reboot_requested, edited));
+                            }
+                            Ok(None) => {}
+                            Err(err) => app.show_error(err),
+                        }
+                    }
+                    // Staged provisioning: enable the repositories now, install packages later.
+                    KeyCode::Char('e') => {
+                        // Resolved items, so repositories auto-added as prerequisites count too.
+                        let (items, _) = app.resolved_items();
+                        if items.iter().all(|item| item.category == ScriptCategory::General) {
+                            app.info_popup = Some(("Enable Repos Only".to_string(), "No repositories are selected.".to_string()));
+                        } else {
+                            return Ok(ActionAfterExit::RunScript { script: app.render_script(false, true, false), saved_path: None, two_phase: false });
+                        }
+                    }
+                    KeyCode::Char('=') => {
+                        let mut items = Vec::new();
+                        app.menu_tree.borrow().get_selected_items_info(&mut items);
+                        if let Some(item) = items.get(app.finished_index) {
+                            app.pin_form = Some(PinForm::new(item.id, &item.name, app.version_pins.get(item.id)));
+                            app.state = AppState::Pinning;
+                        }
+                    }
+                    KeyCode::Char('-') => {
+                        let mut items = Vec::new();
+                        app.menu_tree.borrow().get_selected_items_info(&mut items);
+                        if let Some(item) = items.get(app.finished_index) {
+                            if dnf::group_name(&(item.script_fn)(&app.script_ctx)).is_some() {
+                                let current = app.group_excludes.get(item.id).map(|excludes| excludes.join(" "));
+                                app.exclude_input = TextInput::new(current.unwrap_or_default());
+                                app.exclude_target = Some(item.id);
+                                app.state = AppState::Excluding;
+                            } else {
+                                app.info_popup = Some(("Group Excludes".to_string(), format!("{} does not install a package group.", item.name)));
+                            }
+                        }
+                    }
                     KeyCode::Esc | KeyCode::Backspace => app.state = AppState::Running,
                     _ => {}
                 },
+                AppState::Pinning => match key.code {
+                    KeyCode::Esc => { app.pin_form = None; app.state = AppState::Finished; },
+                    KeyCode::Enter => {
+                        if let Some(form) = app.pin_form.as_mut() {
+                            match form.pin() {
+                                Ok(pin) => {
+                                    match pin {
+                                        Some(pin) => app.version_pins.insert(form.item_id, pin),
+                                        None => app.version_pins.remove(form.item_id),
+                                    };
+                                    app.pin_form = None;
+                                    app.state = AppState::Finished;
+                                    app.invalidate_script();
+                                }
+                                Err(err) => form.error = Some(err),
+                            }
+                        }
+                    }
+                    code => {
+                        if let Some(form) = app.pin_form.as_mut() {
+                            form.handle_key(code);
+                        }
+                    }
+                },
+                AppState::Excluding => match key.code {
+                    KeyCode::Esc => { app.exclude_target = None; app.state = AppState::Finished; },
+                    KeyCode::Enter => {
+                        if let Some(id) = app.exclude_target.take() {
+                            let excludes: Vec<String> = app.exclude_input.value.split_whitespace().map(str::to_string).collect();
+                            if excludes.is_empty() {
+                                app.group_excludes.remove(id);
+                            } else {
+                                app.group_excludes.insert(id, excludes);
+                            }
+                        }
+                        app.state = AppState::Finished;
+                        app.invalidate_script();
+                    }
+                    code => { app.exclude_input.handle_key(code); }
+                },
+                AppState::Tagging => {
+                    let tags = app.all_tags();
+                    match key.code {
+                        KeyCode::Down if !tags.is_empty() => app.tag_index = (app.tag_index + 1) % tags.len(),
+                        KeyCode::Up if !tags.is_empty() => app.tag_index = (app.tag_index + tags.len() - 1) % tags.len(),
+                        KeyCode::Char(' ') => {
+                            if let Some(tag) = tags.get(app.tag_index)
+                                && !app.tag_filter.remove(tag)
+                            {
+                                app.tag_filter.insert(tag);
+                            }
+                            app.selected_index = 0;
+                        }
+                        KeyCode::Esc | KeyCode::Enter | KeyCode::Char('T') => app.state = AppState::Running,
+                        _ => {}
+                    }
+                }
+                AppState::Expanding => match key.code {
+                    KeyCode::Esc => { app.member_picker = None; app.state = AppState::Running; },
+                    KeyCode::Enter => {
+                        if let Some(picker) = app.member_picker.take() {
+                            match picker.chosen() {
+                                Some(members) => app.group_members.insert(picker.item_id, members),
+                                None => app.group_members.remove(picker.item_id),
+                            };
+                        }
+                        app.state = AppState::Running;
+                        app.invalidate_script();
+                    }
+                    code => {
+                        if let Some(picker) = app.member_picker.as_mut() {
+                            picker.handle_key(code);
+                        }
+                    }
+                },
+                AppState::Scheduling => match key.code {
+                    KeyCode::Esc => app.state = AppState::Finished,
+                    KeyCode::Enter => {
+                        let text = app.reboot_at_input.value.trim();
+                        if text.is_empty() {
+                            app.reboot_at = None;
+                            app.state = AppState::Finished;
+                            app.invalidate_script();
+                        } else {
+                            match parse_reboot_time(text) {
+                                Ok(time) => {
+                                    app.reboot_at = Some(time);
+                                    app.reboot_requested = true;
+                                    app.state = AppState::Finished;
+                                    app.invalidate_script();
+                                }
+                                Err(err) => app.show_error(err),
+                            }
+                        }
+                    }
+                    code => { app.reboot_at_input.handle_key(code); }
+                },
+                AppState::Report => match key.code {
+                    KeyCode::Up => app.report_scroll = app.report_scroll.saturating_sub(1),
+                    KeyCode::Down => app.report_scroll = app.report_scroll.saturating_add(1),
+                    KeyCode::PageUp => app.report_scroll = app.report_scroll.saturating_sub(10),
+                    KeyCode::PageDown => app.report_scroll = app.report_scroll.saturating_add(10),
+                    _ => app.state = AppState::Running,
+                },
+                AppState::Searching => {
+                    let visible_nodes = get_visible_nodes(&app);
+                    match key.code {
+                        KeyCode::Esc => {
+                            app.search_input.clear();
+                            app.selected_index = 0;
+                            app.state = AppState::Running;
+                        }
+                        KeyCode::Down if !visible_nodes.is_empty() => {
+                            app.selected_index = (app.selected_index + 1).min(visible_nodes.len() - 1);
+                        }
+                        KeyCode::Up => app.selected_index = app.selected_index.saturating_sub(1),
+                        KeyCode::Enter => {
+                            if let Some((_, selected_rc)) = visible_nodes.get(app.selected_index) {
+                                let unavailable = match &*selected_rc.borrow() {
+                                    MenuNode::Item { name, availability: Availability::Unavailable(reason), .. } => Some((name.clone(), *reason)),
+                                    _ => None,
+                                };
+                                match unavailable {
+                                    Some((name, reason)) => app.info_popup = Some((name, format!("This item cannot be selected: {}.", reason))),
+                                    None => app.toggle_item(selected_rc),
+                                }
+                            }
+                        }
+                        code => {
+                            if app.search_input.handle_key(code) {
+                                app.selected_index = 0;
+                            }
+                        }
+                    }
+                }
+                AppState::Timer => match key.code {
+                    KeyCode::Esc => app.state = AppState::Finished,
+                    // A recurring run must not reboot the machine each time.
+                    KeyCode::Enter => match runner::validate_schedule(&app.schedule_input.value) {
+                        Ok(schedule) => return Ok(ActionAfterExit::InstallTimer { script: app.script(false), schedule }),
+                        Err(err) => app.show_error(err),
+                    },
+                    code => { app.schedule_input.handle_key(code); }
+                },
+                AppState::Noting => match key.code {
+                    KeyCode::Esc => { app.note_target = None; app.state = AppState::Running; },
+                    KeyCode::Enter => {
+                        if let Some(node) = app.note_target.take()
+                            && let MenuNode::Item { note, .. } = &mut *node.borrow_mut()
+                        {
+                            let text = app.note_input.value.trim();
+                            *note = if text.is_empty() { None } else { Some(text.to_string()) };
+                        }
+                        app.state = AppState::Running;
+                        app.invalidate_script();
+                    }
+                    code => { app.note_input.handle_key(code); }
+                },
+                AppState::Profiles => {
+                    let action = app.profile_manager.as_mut().and_then(|manager| manager.handle_key(key.code));
+                    let result = match action {
+                        Some(ProfileAction::Close) => Ok(None),
+                        Some(ProfileAction::Save(name)) => app.current_profile().save(&name)
+                            .map(|path| Some(format!("Saved profile '{}' to {}", name, path.display()))),
+                        Some(ProfileAction::Load(name)) => Profile::load(&name)
+                            .and_then(|profile| app.apply_profile(&profile))
+                            .map(|()| Some(format!("Loaded profile '{}'", name))),
+                        None => continue,
+                    };
+                    match result {
+                        Ok(message) => {
+                            app.profile_manager = None;
+                            app.state = AppState::Running;
+                            app.info_popup = message.map(|message| ("Profiles".to_string(), message));
+                        }
+                        Err(err) => {
+                            if let Some(manager) = app.profile_manager.as_mut() {
+                                manager.error = Some(err);
+                            }
+                        }
+                    }
+                }
                 AppState::Saving => match key.code {
-                    KeyCode::Char(c) => app.filename_input.push(c),
-                    KeyCode::Backspace => { app.filename_input.pop(); },
                     KeyCode::Esc => { app.state = AppState::Finished; app.filename_input.clear(); app.save_status_message = None; },
+                    // Splitting is for handing the files on; "save and run" needs the single script.
+                    KeyCode::Tab if !app.save_and_run => app.split_output = !app.split_output,
+                    KeyCode::Enter if app.split_output && !app.save_and_run => {
+                        let base = app.filename_input.value.trim_end_matches(".sh").to_string();
+                        let mut saved = Vec::new();
+                        for (category, script) in app.generate_split(app.reboot_requested) {
+                            let path = PathBuf::from(format!("{}-{}.sh", base, category));
+                            if let Err(e) = fs::write(&path, script) {
+                                saved.push(format!("Error writing {}: {}", path.display(), e));
+                                break;
+                            }
+                            saved.push(format!("Saved {} to {}", category, path.display()));
+                            app.saved_paths.push(path);
+                        }
+                        if saved.is_empty() {
+                            saved.push("No options selected; nothing was saved.".to_string());
+                        }
+                        app.set_status(saved.join("\n"));
+                        app.state = AppState::Finished;
+                        app.filename_input.clear();
+                    }
+                    KeyCode::Enter => {
+                        let script = app.script(app.reboot_requested);
+                        let path = PathBuf::from(&app.filename_input.value);
+                        let saved = fs::write(&path, &script);
+                        match &saved {
+                            Ok(_) => {
+                                app.set_status(format!("Saved to {}", path.display()));
+                                app.saved_paths.push(path.clone());
+                            }
+                            Err(e) => app.set_status(format!("Error: {}", e)),
+                        }
+                        app.state = AppState::Finished;
+                        app.filename_input.clear();
+
+                        if app.save_and_run && saved.is_ok() {
+                            app.save_and_run = false;
+                            if !app.reboot_requested {
+                                return Ok(ActionAfterExit::RunScript { script, saved_path: Some(path), two_phase: app.two_phase });
+                            }
+                            app.run_saved_path = Some(path);
+                            if let Some(action) = app.confirm(Confirmation::RunWithReboot) {
+                                return Ok(action);
+                            }
+                        }
+                    }
+                    code => { app.filename_input.handle_key(code); }
+                },
+                AppState::Settings => match key.code {
+                    KeyCode::Esc => { app.settings_form = None; app.state = AppState::Running; },
                     KeyCode::Enter => {
-                        let script = app.generate_commands(app.reboot_requested);
-                        match fs::write(&app.filename_input, script) {
-                            Ok(_) => app.save_status_message = Some(format!("Saved to {}", app.filename_input)),
-                            Err(e) => app.save_status_message = Some(format!("Error: {}", e)),
+                        if let Some(form) = app.settings_form.as_mut() {
+                            match form.apply(&mut app.script_ctx) {
+                                Ok(()) => {
+                                    app.syntax_highlight = form.syntax_highlight();
+                                    app.allow_erasing = form.allow_erasing();
+                                    app.nobest = form.nobest();
+                                    app.settings_form = None;
+                                    app.state = AppState::Running;
+                                    app.invalidate_script();
+                                }
+                                Err(err) => form.error = Some(err),
+                            }
+                        }
+                    }
+                    code => {
+                        if let Some(form) = app.settings_form.as_mut() {
+                            form.handle_key(code);
+                        }
+                    }
+                },
+                AppState::Confirm(confirmation) => match key.code {
+                    KeyCode::Char('y') | KeyCode::Enter => {
+                        app.state = app.previous_state.clone();
+                        if let Some(action) = app.accept(confirmation) {
+                            return Ok(action);
                         }
-                        app.state = AppState::Finished;
-                        app.filename_input.clear();
                     }
+                    KeyCode::Char('n') | KeyCode::Esc => app.state = app.previous_state.clone(),
                     _ => {}
-                }
+                },
+                AppState::Error(_) => app.state = app.previous_state.clone(),
             }
         }
     }
 }
 
 fn ui(f: &mut Frame, app: &mut App) {
-    match app.state {
-        AppState::Finished | AppState::Saving => {
-            draw_finished_screen(f, app);
-            if let AppState::Saving = app.state {
-                draw_saving_popup(f, &app.filename_input);
+    let screen = match &app.state {
+        AppState::Confirm(_) | AppState::Error(_) => &app.previous_state,
+        state => state,
+    };
+    if matches!(screen, AppState::Finished | AppState::Saving | AppState::Pinning | AppState::Excluding | AppState::Scheduling | AppState::Timer) {
+        draw_finished_screen(f, app);
+        if let AppState::Saving = app.state {
+            if app.save_and_run {
+                draw_text_popup(f, "Save Script", "Enter filename (press Enter to save, Esc to cancel):", &app.filename_input);
+            } else if app.split_output {
+                let prompt = "Base name for <name>-repos.sh and <name>-packages.sh ([Tab] single file):";
+                draw_text_popup(f, "Save Script (one file per category)", prompt, &app.filename_input);
+            } else {
+                let prompt = "Enter filename (Enter saves, [Tab] one file per category, Esc cancels):";
+                draw_text_popup(f, "Save Script", prompt, &app.filename_input);
             }
-        },
-        AppState::Running => {
-            draw_main_ui(f, app);
         }
+    } else {
+        draw_main_ui(f, app);
+    }
+    if let Some(form) = &app.settings_form {
+        settings::draw_settings_popup(f, form, centered_rect(60, 80, f.size()));
+    }
+    if let AppState::Excluding = app.state {
+        let prompt = "Packages to leave out of this group, space-separated; globs such as libreoffice* work (empty clears):";
+        draw_text_popup(f, "Group Excludes", prompt, &app.exclude_input);
+    }
+    if let Some(picker) = &app.member_picker {
+        members::draw_member_popup(f, picker, centered_rect(60, 70, f.size()));
+    }
+    if let AppState::Tagging = app.state {
+        draw_tag_popup(f, &app.all_tags(), &app.tag_filter, app.tag_index);
+    }
+    if let AppState::Scheduling = app.state {
+        draw_text_popup(f, "Scheduled Reboot", "Reboot at HH:MM (Enter to set, empty reboots right away, Esc to cancel):", &app.reboot_at_input);
+    }
+    if let AppState::Timer = app.state {
+        draw_text_popup(f, "Schedule", "Run the script on a systemd calendar schedule, e.g. weekly or Sun *-*-* 03:00:", &app.schedule_input);
+    }
+    if let AppState::Noting = app.state {
+        draw_text_popup(f, "Note", "Note for this item (Enter to save, empty clears, Esc to cancel):", &app.note_input);
+    }
+    if let Some(form) = &app.pin_form {
+        pins::draw_pin_popup(f, form, centered_rect(60, 40, f.size()));
+    }
+    if let Some(manager) = &app.profile_manager {
+        profile::draw_profiles_popup(f, manager, centered_rect(50, 60, f.size()));
+    }
+    if let AppState::Confirm(confirmation) = app.state {
+        draw_confirm_popup(f, confirmation, app.over_ssh, app.reboot_at.as_deref());
+    }
+    if let AppState::Error(message) = &app.state {
+        draw_error_popup(f, message);
+    }
+    if let Some((title, body)) = &app.info_popup {
+        draw_info_popup(f, title, body);
+    }
+    if let Some(lookup) = &app.group_lookup {
+        draw_loading_popup(f, &lookup.group);
+    }
+    if let AppState::Report = app.state {
+        draw_report_popup(f, &app.change_report, app.report_scroll);
+    }
+    if let Some((name, details)) = &app.detail_popup {
+        draw_detail_popup(f, name, details, app.detail_scroll);
+    }
+    if app.show_help {
+        draw_help_popup(f);
     }
 }
 
+/// Draws the generated script preview. It shows exactly what would run, including the
+/// reboot block when one is queued.
+fn draw_preview(f: &mut Frame, app: &mut App, area: Rect) {
+    let mut script_content = app.script_text(app.reboot_requested);
+    let preview_title = if app.reboot_requested {
+        script_content.lines.push(Line::from(""));
+        script_content.lines.push(Line::from(Span::styled(
+            ">>> REBOOT QUEUED: the system restarts when the script finishes <<<",
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        )));
+        "Generated Script Preview (with Reboot)"
+    } else {
+        "Generated Script Preview"
+    };
+    let script_preview = Paragraph::new(script_content)
+        .wrap(Wrap { trim: true })
+        .block(Block::default().borders(Borders::ALL).title(preview_title));
+    f.render_widget(script_preview, area);
+}
+
+/// Terminal width from which the preview moves into a third column beside the menu.
+const WIDE_LAYOUT_MIN_WIDTH: u16 = 180;
+
 fn draw_main_ui(f: &mut Frame, app: &mut App) {
+    let wide = f.size().width >= WIDE_LAYOUT_MIN_WIDTH;
+    // Hiding the preview hands its row or column to the menu and selection lists.
+    let preview_height = if wide || !app.show_preview { Constraint::Length(0) } else { Constraint::Percentage(40) };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3), // Title
             Constraint::Min(0), // Main content area
-            Constraint::Percentage(40), // Script preview
+            preview_height, // Script preview (moved into a column on wide terminals)
             Constraint::Length(3), // Footer
         ].as_ref())
         .split(f.size());
 
-    let path_str = {
-        app.nav_path.iter().map(|node_rc| {
-            let node = node_rc.borrow();
-            match &*node {
-                MenuNode::Menu { name, .. } => name.clone(),
-                MenuNode::Item { name, .. } => name.clone(),
-            }
-        }).collect::<Vec<_>>().join(" > ")
+    let tags = app.tag_filter.iter().copied().collect::<Vec<_>>().join(", ");
+    let path_str = match app.category_filter {
+        _ if !app.search_input.value.trim().is_empty() => format!("Search: {} [Esc] Tree view", app.search_input.value.trim()),
+        Some(ScriptCategory::General) if !tags.is_empty() => format!("Filter: General items tagged {} [0] All", tags),
+        Some(ScriptCategory::General) => "Filter: General items [0] All".to_string(),
+        Some(_) if !tags.is_empty() => format!("Filter: Repositories tagged {} [0] All", tags),
+        Some(_) => "Filter: Repositories [0] All".to_string(),
+        None if !tags.is_empty() => format!("Filter: Tagged {} [T] Change [0] All", tags),
+        None if app.flat_view => "All items (A-Z) [v] Tree view".to_string(),
+        None => {
+            let path = app.nav_path.iter().map(|node_rc| node_rc.borrow().name().to_string()).collect::<Vec<_>>().join(" > ");
+            if app.recent_first { format!("{} (recently used first)", path) } else { path }
+        }
     };
 
-    let title_text = format!("Enterprise Linux TUI (Detected: {:?})", app.os_distro);
-    let title = Paragraph::new(title_text).style(Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))
-        .block(Block::default().borders(Borders::ALL));
-    f.render_widget(title, chunks[0]);
+    let mode = if app.no_sudo { " [no-sudo]" } else { "" };
+    let target = if app.os_distro == app.detected_os { String::new() } else { format!(", Target: {:?}", app.os_distro) };
+    let (repos, packages) = app.selection_counts();
+    let privilege = match app.privilege {
+        Privilege::Root => " [root]",
+        Privilege::Sudo => "",
+        Privilege::NoSudo => " [no sudo: running will fail]",
+    };
+    let detected = match app.os_version {
+        Some(version) => format!("{:?} {}", app.detected_os, version),
+        None => format!("{:?}", app.detected_os),
+    };
+    let title_text = format!(
+        "Enterprise Linux TUI (Detected: {}{}) | {} repos, {} pkgs selected{}{}",
+        detected, target, repos, packages, mode, privilege,
+    );
+    let title_style = Style::default().fg(Color::Green).add_modifier(Modifier::BOLD);
+    let tabs = Tabs::new(app.tab_titles())
+        .select(app.active_tab)
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD | Modifier::REVERSED))
+        .block(Block::default().title(Span::styled(title_text, title_style)).borders(Borders::ALL));
+    f.render_widget(tabs, chunks[0]);
 
+    let columns = if wide && app.show_preview {
+        [Constraint::Percentage(35), Constraint::Percentage(25), Constraint::Percentage(40)].to_vec()
+    } else {
+        [Constraint::Percentage(50), Constraint::Percentage(50)].to_vec()
+    };
     let main_chunks = Layout::default().direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .constraints(columns)
         .split(chunks[1]);
+    let preview_area = if wide && app.show_preview { main_chunks[2] } else { chunks[2] };
 
-    let visible_nodes = get_visible_nodes(&app.nav_path);
-    let menu_items: Vec<ListItem> = visible_nodes.iter().map(|(text, _)| ListItem::new(text.clone())).collect();
+    let visible_nodes = get_visible_nodes(app);
+    let menu_items: Vec<ListItem> = visible_nodes.iter().map(|(text, node)| {
+        let item = ListItem::new(text.clone());
+        match &*node.borrow() {
+            MenuNode::Item { availability: Availability::Unavailable(_), .. } => item.style(Style::default().fg(Color::DarkGray)),
+            MenuNode::Item { meta, .. } if meta.is_stub => item.style(Style::default().add_modifier(Modifier::DIM)),
+            _ => item,
+        }
+    }).collect();
 
     if !visible_nodes.is_empty() {
         app.selected_index = app.selected_index.min(visible_nodes.len() - 1);
@@ -389,22 +3018,80 @@ fn draw_main_ui(f: &mut Frame, app: &mut App) {
     let selected_list = List::new(selected_items).block(Block::default().borders(Borders::ALL).title("Selected Components"));
     f.render_widget(selected_list, main_chunks[1]);
 
-    let script_content = app.generate_commands(false);
-    let script_preview = Paragraph::new(script_content)
-        .wrap(Wrap { trim: true })
-        .block(Block::default().borders(Borders::ALL).title("Generated Script Preview"));
-    f.render_widget(script_preview, chunks[2]);
+    if app.show_preview {
+        draw_preview(f, app, preview_area);
+    }
 
-    let footer_text = "Navigate [←→↑↓] | Select [Enter] | [i] Generate Script | [q] Quit";
+    draw_status_popup(f, app);
+
+    if let AppState::Searching = app.state {
+        let title = "Search items ([↑↓] Move | [Enter] Select | [Esc] Clear)";
+        f.render_widget(app.search_input.widget(title, true), chunks[3]);
+        return;
+    }
+    let arrows = if app.ascii { "arrows" } else { "←→↑↓" };
+    let footer_text = format!("Navigate [{}] | Select [Enter] | [u] No-sudo Mode | [p] Preview | [i] Generate Script | [?] Help | [q] Quit", arrows);
     let footer = Paragraph::new(footer_text).style(Style::default().fg(Color::Cyan))
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(footer, chunks[3]);
 }
 
+/// The index of the next item row after `from` (or before it, going back), skipping menu rows.
+fn next_item_index(nodes: &[(String, Rc<RefCell<MenuNode>>)], from: usize, forward: bool, wrap: bool) -> Option<usize> {
+    let len = nodes.len();
+    let steps = if wrap { len.saturating_sub(1) } else if forward { len.saturating_sub(from + 1) } else { from };
+    (1..=steps)
+        .map(|step| if forward { (from + step) % len } else { (from + len - step) % len })
+        .find(|&index| matches!(&*nodes[index].1.borrow(), MenuNode::Item { .. }))
+}
+
+/// The index of the next top-level row after `from` (or before it, going back), wrapping
+/// around. Top-level rows are the ones whose tree connector has no indentation in front.
+fn next_section_index(nodes: &[(String, Rc<RefCell<MenuNode>>)], from: usize, forward: bool, glyphs: &TreeGlyphs) -> Option<usize> {
+    let len = nodes.len();
+    (1..len)
+        .map(|step| if forward { (from + step) % len } else { (from + len - step) % len })
+        .find(|&index| nodes[index].0.starts_with(glyphs.branch) || nodes[index].0.starts_with(glyphs.last))
+}
+
+fn get_visible_nodes(app: &App) -> Vec<(String, Rc<RefCell<MenuNode>>)> {
+    let mut items = collect_visible_nodes(app);
+    for (text, node) in &mut items {
+        if let MenuNode::Item { id, meta, .. } = &*node.borrow() {
+            if meta.is_stub {
+                text.push_str(" (stub)");
+            }
+            if app.no_auto_include.contains(id) {
+                text.push_str(" (never auto-added)");
+            }
+            if app.show_ids {
+                text.push_str(&format!(" [{}]", id));
+            }
+        }
+    }
+    items
+}
+
 /// Generates the list of visible nodes with tree-style formatting.
-fn get_visible_nodes(nav_path: &[Rc<RefCell<MenuNode>>]) -> Vec<(String, Rc<RefCell<MenuNode>>)> {
+fn collect_visible_nodes(app: &App) -> Vec<(String, Rc<RefCell<MenuNode>>)> {
     let mut items = Vec::new();
+    let query = app.search_input.value.trim().to_lowercase();
+    if !query.is_empty() {
+        collect_filtered(&mut items, &app.menu_tree, None, false, app.category_filter, &app.tag_filter, app.glyphs());
+        items.retain(|(_, node)| node.borrow().name().to_lowercase().contains(&query));
+        return items;
+    }
+    if app.category_filter.is_some() || app.flat_view || !app.tag_filter.is_empty() {
+        collect_filtered(&mut items, &app.menu_tree, None, false, app.category_filter, &app.tag_filter, app.glyphs());
+        if app.flat_view {
+            items.sort_by_cached_key(|(_, node)| node.borrow().name().to_lowercase());
+        }
+        return items;
+    }
+    let nav_path = &app.nav_path;
     let current_menu = nav_path.last().unwrap();
+    let glyphs = app.glyphs();
+    let recent = app.recent_first.then_some(&app.history);
 
     // This recursive helper function builds the tree structure.
     fn build_tree_display(
@@ -412,103 +3099,372 @@ fn get_visible_nodes(nav_path: &[Rc<RefCell<MenuNode>>]) -> Vec<(String, Rc<RefC
         node: &Rc<RefCell<MenuNode>>,
         prefix: &str,
         is_last: bool,
+        in_radio: bool,
+        glyphs: &TreeGlyphs,
+        recent: Option<&History>,
     ) {
         let node_borrow = node.borrow();
-        let connector = if is_last { "└─" } else { "├─" };
+        let connector = if is_last { glyphs.last } else { glyphs.branch };
         let line = format!("{}{}", prefix, connector);
+        items.push((format!("{} {}", line, node_label(&node_borrow, in_radio, glyphs)), node.clone()));
 
-        match &*node_borrow {
-            MenuNode::Menu { name, children } => {
-                items.push((format!("{} {} >", line, name), node.clone()));
-                
-                let new_prefix = if is_last {
-                    format!("{}   ", prefix)
-                } else {
-                    format!("{}│  ", prefix)
-                };
-                
-                let num_children = children.len();
-                for (i, child) in children.iter().enumerate() {
-                    build_tree_display(items, child, &new_prefix, i == num_children - 1);
-                }
-            }
-            MenuNode::Item { name, selected, .. } => {
-                let prefix_icon = if *selected { "[x]" } else { "[ ]" };
-                items.push((format!("{} {} {}", line, prefix_icon, name), node.clone()));
+        if let Some(children) = node_borrow.children() {
+            let new_prefix = if is_last {
+                format!("{}   ", prefix)
+            } else {
+                format!("{}{}", prefix, glyphs.pipe)
+            };
+            let is_radio = matches!(&*node_borrow, MenuNode::Radio { .. });
+
+            let num_children = children.len();
+            for (i, child) in ordered_children(children, recent).iter().enumerate() {
+                build_tree_display(items, child, &new_prefix, i == num_children - 1, is_radio, glyphs, recent);
             }
         }
     }
 
-    if let MenuNode::Menu { children, .. } = &*current_menu.borrow() {
+    let current_borrow = current_menu.borrow();
+    let in_radio = matches!(&*current_borrow, MenuNode::Radio { .. });
+    if let Some(children) = current_borrow.children() {
         // If we are at the root, render the full tree recursively.
         if nav_path.len() == 1 {
             let num_children = children.len();
-            for (i, child) in children.iter().enumerate() {
-                build_tree_display(&mut items, child, "", i == num_children - 1);
+            for (i, child) in ordered_children(children, recent).iter().enumerate() {
+                build_tree_display(&mut items, child, "", i == num_children - 1, in_radio, glyphs, recent);
             }
         } else {
             // If we are in a submenu, render a simple list but still use tree connectors.
             let num_children = children.len();
-            for (i, child) in children.iter().enumerate() {
-                let node_borrow = child.borrow();
-                let connector = if i == num_children - 1 { "└─" } else { "├─" };
-                match &*node_borrow {
-                    MenuNode::Menu { name, .. } => {
-                        items.push((format!("{} {} >", connector, name), child.clone()));
-                    }
-                    MenuNode::Item { name, selected, .. } => {
-                        let prefix_icon = if *selected { "[x]" } else { "[ ]" };
-                        items.push((format!("{} {} {}", connector, prefix_icon, name), child.clone()));
-                    }
-                }
+            for (i, child) in ordered_children(children, recent).iter().enumerate() {
+                let connector = if i == num_children - 1 { glyphs.last } else { glyphs.branch };
+                items.push((format!("{} {}", connector, node_label(&child.borrow(), in_radio, glyphs)), child.clone()));
             }
         }
     }
     items
 }
 
+/// Flattens the tree into the items matching `filter` (all items for `None`) and carrying
+/// one of `tags` (any tags when empty), each labelled with its menu path.
+fn collect_filtered(
+    items: &mut Vec<(String, Rc<RefCell<MenuNode>>)>,
+    node: &Rc<RefCell<MenuNode>>,
+    parent_path: Option<&str>,
+    in_radio: bool,
+    filter: Option<ScriptCategory>,
+    tags: &BTreeSet<&'static str>,
+    glyphs: &TreeGlyphs,
+) {
+    let node_borrow = node.borrow();
+    match &*node_borrow {
+        MenuNode::Item { category, meta, .. } => {
+            if filter.is_none_or(|filter| category.matches_filter(filter))
+                && (tags.is_empty() || meta.tags.iter().any(|tag| tags.contains(tag)))
+            {
+                let label = node_label(&node_borrow, in_radio, glyphs);
+                items.push((format!("{}  ({})", label, parent_path.unwrap_or_default()), node.clone()));
+            }
+        }
+        MenuNode::Menu { name, children } | MenuNode::Radio { name, children } => {
+            let path = match parent_path {
+                None => String::new(),
+                Some("") => name.clone(),
+                Some(path) => format!("{} > {}", path, name),
+            };
+            let is_radio = matches!(&*node_borrow, MenuNode::Radio { .. });
+            for child in children {
+                collect_filtered(items, child, Some(&path), is_radio, filter, tags, glyphs);
+            }
+        }
+    }
+}
+
+/// Formats a single row: a checkbox (or radio button) for items, a badge for menus.
+fn node_label(node: &MenuNode, in_radio: bool, glyphs: &TreeGlyphs) -> String {
+    match node {
+        MenuNode::Item { name, availability: Availability::Unavailable(reason), .. } => {
+            format!("[-] {} ({})", name, reason)
+        }
+        MenuNode::Item { name, selected, meta, .. } => {
+            let prefix_icon = match (in_radio, *selected) {
+                (false, true) => "[x]",
+                (false, false) => "[ ]",
+                (true, true) => "(*)",
+                (true, false) => "( )",
+            };
+            format!("{} {}{}", prefix_icon, name, weight_suffix(meta, glyphs))
+        }
+        MenuNode::Menu { name, .. } => menu_label(name, node),
+        MenuNode::Radio { name, .. } => menu_label(&format!("{} (choose one)", name), node),
+    }
+}
+
 
 fn draw_finished_screen(f: &mut Frame, app: &mut App) {
     // FIX: Changed Constraint.Length to Constraint::Length
+    let reboot_reasons = app.reboot_reasons();
+    let notice_height = if reboot_reasons.is_empty() { 0 } else { 3 };
+    let stubs = app.selected_stubs();
+    let stub_height = if stubs.is_empty() { 0 } else { 3 };
     let chunks = Layout::default().direction(Direction::Vertical)
-        .constraints([Constraint::Min(0), Constraint::Length(3)].as_ref()).split(f.size());
-    let script_content = app.generate_commands(app.reboot_requested);
-    let title = if app.reboot_requested { "Installation Script (with Reboot)" } else { "Installation Script" };
-    let paragraph = Paragraph::new(script_content).wrap(Wrap { trim: true })
-        .block(Block::default().title(title).borders(Borders::ALL));
-    f.render_widget(paragraph, chunks[0]);
-
-    if let Some(msg) = &app.save_status_message {
-        let msg_p = Paragraph::new(msg.as_str()).style(Style::default().fg(Color::Yellow));
-        let area = centered_rect(50, 10, f.size());
-        f.render_widget(Clear, area);
-        f.render_widget(msg_p.block(Block::default().borders(Borders::ALL).title("Status")), area);
-        if app.filename_input.is_empty() { 
-             app.save_status_message = None;
-        }
+        .constraints([Constraint::Length(notice_height), Constraint::Length(stub_height), Constraint::Min(0), Constraint::Length(4)].as_ref())
+        .split(f.size());
+    if !reboot_reasons.is_empty() {
+        let (hint, style) = if app.reboot_requested {
+            ("The script will reboot when done ([b] turns this off, [B] schedules it).", Style::default().fg(Color::Green))
+        } else {
+            ("Press [b] to reboot when the script finishes.", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        };
+        let notice = Paragraph::new(format!("Reboot recommended because: {}. {}", reboot_reasons.join(", "), hint))
+            .style(style).wrap(Wrap { trim: true }).block(Block::default().borders(Borders::ALL));
+        f.render_widget(notice, chunks[0]);
+    }
+    if !stubs.is_empty() {
+        let warning = Paragraph::new(format!("Placeholders only, nothing will be installed for: {}.", stubs.join(", ")))
+            .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+            .wrap(Wrap { trim: true }).block(Block::default().borders(Borders::ALL));
+        f.render_widget(warning, chunks[1]);
+    }
+    let chunks = &chunks[2..];
+    let mut title = if app.reboot_requested { "Installation Script (with Reboot)" } else { "Installation Script" }.to_string();
+    if app.edited_script.as_ref().is_some_and(|(reboot, _)| *reboot == app.reboot_requested) {
+        // The per-item review shows generated steps; only the full script view shows the edit.
+        title.push_str(" [edited in $EDITOR]");
+    }
+    if app.show_full_script {
+        let script_content = app.script_text(app.reboot_requested);
+        let paragraph = Paragraph::new(script_content).wrap(Wrap { trim: true })
+            .block(Block::default().title(title).borders(Borders::ALL));
+        f.render_widget(paragraph, chunks[0]);
+    } else {
+        draw_item_review(f, app, chunks[0], &title);
     }
 
-    let footer_text = "Review Script | [s] Save to File | [r] Run Directly | [q] Quit | [Esc/Backspace] Go Back";
-    let footer = Paragraph::new(footer_text).style(Style::default().fg(Color::Cyan))
+    draw_status_popup(f, app);
+
+    let two_phase = if app.two_phase { "on" } else { "off" };
+    let reboot = match (&app.reboot_at, app.reboot_requested) {
+        (Some(time), true) => format!("at {}", time),
+        (_, reboot) => if reboot { "on" } else { "off" }.to_string(),
+    };
+    // The settings and the common actions; [?] lists the rest of the review keys.
+    let footer_text = format!("[o] Format: {} | [t] Two-phase Run: {} | [b] Reboot: {} | [p] Save Profile\n\
+        [s] Save | [r] Run | [x] Save & Run | [q] Quit | [Esc] Back | [?] All Keys",
+        app.output_format.label(), two_phase, reboot);
+    let footer = Paragraph::new(footer_text).style(Style::default().fg(Color::Cyan)).wrap(Wrap { trim: true })
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(footer, chunks[1]);
 }
 
-fn draw_saving_popup(f: &mut Frame, input: &str) {
+/// Draws the per-item review: selected items on the left, the highlighted item's commands on the right.
+fn draw_item_review(f: &mut Frame, app: &mut App, area: Rect, title: &str) {
+    let panes = Layout::default().direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+        .split(area);
+
+    let mut items = Vec::new();
+    app.menu_tree.borrow().get_selected_items_info(&mut items);
+    app.finished_index = app.finished_index.min(items.len().saturating_sub(1));
+
+    let list_items: Vec<ListItem> = items.iter().map(|i| ListItem::new(i.qualified_name.clone())).collect();
+    let list = List::new(list_items)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD).bg(Color::DarkGray))
+        .highlight_symbol(">> ");
+    let mut list_state = ratatui::widgets::ListState::default();
+    if !items.is_empty() {
+        list_state.select(Some(app.finished_index));
+    }
+    f.render_stateful_widget(list, panes[0], &mut list_state);
+
+    let commands = match items.get(app.finished_index) {
+        Some(item) => app.item_script(item),
+        None => "# No options selected.".to_string(),
+    };
+    let detail = Paragraph::new(commands).wrap(Wrap { trim: false })
+        .block(Block::default().title("Commands").borders(Borders::ALL));
+    f.render_widget(detail, panes[1]);
+}
+
+fn draw_text_popup(f: &mut Frame, title: &str, prompt: &str, input: &TextInput) {
     let area = centered_rect(60, 20, f.size());
-    let block = Block::default().title("Save Script").borders(Borders::ALL);
+    let block = Block::default().title(title).borders(Borders::ALL);
     f.render_widget(Clear, area);
     f.render_widget(block, area);
 
     let popup_chunks = Layout::default().direction(Direction::Vertical).margin(2)
-        .constraints([Constraint::Length(1), Constraint::Length(1), Constraint::Min(1)].as_ref()).split(area);
+        .constraints([Constraint::Length(1), Constraint::Length(3), Constraint::Min(1)].as_ref()).split(area);
     
-    let p1 = Paragraph::new("Enter filename (press Enter to save, Esc to cancel):");
-    let p2 = Paragraph::new(input).block(Block::default().borders(Borders::ALL));
+    let p1 = Paragraph::new(prompt);
+    let p2 = input.widget("", true);
     f.render_widget(p1, popup_chunks[0]);
     f.render_widget(p2, popup_chunks[1]);
 }
 
+fn draw_confirm_popup(f: &mut Frame, confirmation: Confirmation, over_ssh: bool, reboot_at: Option<&str>) {
+    let area = centered_rect(50, 30, f.size());
+    let mut text = confirmation.prompt().to_string();
+    if confirmation == Confirmation::RunWithReboot && let Some(time) = reboot_at {
+        text.push_str(&format!("\n\nThe reboot is scheduled for {}; the installs run now.", time));
+    } else if confirmation == Confirmation::RunWithReboot && over_ssh {
+        text.push_str("\n\nYou are connected over SSH: the connection will drop when the reboot starts. \
+            The reboot is scheduled 10 seconds after the last step so the session can close cleanly.");
+    }
+    let text = format!("{}\n\n[y/Enter] Yes | [n/Esc] No", text);
+    let popup = Paragraph::new(text).wrap(Wrap { trim: true }).style(Style::default().fg(Color::Yellow))
+        .block(Block::default().title("Confirm").borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+/// Key bindings shown in the help overlay.
+const HELP_LINES: &[&str] = &[
+    "Main menu",
+    "  ←→              Switch tabs (at a tab's top level)",
+    "  ←→↑↓ / Enter    Navigate and toggle items",
+    "  n / N           Jump to the next / previous item, skipping menus",
+    "  Tab / Shift-Tab Jump to the next / previous top-level section",
+    "  f               Forward into the menu you just left",
+    "  t               Toggle every item shown on this page",
+    "  x               Invert selections in the current menu (or the rows shown while filtering)",
+    "  a / c           Select / clear every item in the highlighted submenu",
+    "  m               Attach a note to the highlighted item",
+    "  A               Never auto-add the highlighted item as a prerequisite",
+    "  s               Sort menus by recently used / alphabetically",
+    "  v               Switch between tree and flat A-Z list",
+    "  /               Search items by name (Enter selects, Esc returns to the tree)",
+    "  p               Hide or show the script preview",
+    "  1 / 2 / 0       Show only repositories / general items / everything",
+    "  T               Filter by tags (gui, server, network, experimental, ...)",
+    "  u               Toggle no-sudo mode",
+    "  w               Toggle wrap-around navigation",
+    "  o               Settings (EPEL version, flathub URL, dnf tuning, run recording)",
+    "  P               Profiles: save the selection by name, load or delete",
+    "  D               Switch target distro (keeps selections)",
+    "  g               Show members of a group-install item",
+    "  y               Copy the highlighted item's command to the clipboard",
+    "  G               Pick which members of a group install to install",
+    "  d               Explain what the highlighted repository provides",
+    "  W               Report what the selection would change on this machine",
+    "  L               Show the steps, exit codes and durations of the last recorded run",
+    "  i / r           Review script (r: with reboot)",
+    "  q               Quit",
+    "",
+    "Review screen",
+    "  s               Save to file",
+    "  ↑↓ / Tab        Browse items / toggle full script",
+    "  r               Run directly",
+    "  e               Run only the repository section (no packages, no reboot)",
+    "  E               Edit the script in $EDITOR; runs and saves use the edit",
+    "  c               Check the script's syntax with bash -n",
+    "  x               Save to a file, then run it",
+    "  t               Two-phase run: repos first, confirm before installs",
+    "  b               Toggle rebooting when the script finishes",
+    "  B               Schedule the reboot for a time of day (e.g. 03:00)",
+    "  f               Install as a systemd unit that runs on next boot",
+    "  S               Install with a systemd timer that reruns it on a schedule",
+    "  p               Save the selected item names to the --profile file",
+    "  o               Switch output format (bash / Containerfile / cloud-init / multi-distro bash)",
+    "  =               Pin package versions for the highlighted item",
+    "  -               Exclude packages from the highlighted group install",
+    "  Esc/Backspace   Go back",
+    "",
+    "Confirmations (skipped with --yes / -y)",
+    "  Quitting with selected components",
+    "  Running a script that reboots",
+    "  Continuing with phase 2 of a two-phase run",
+    "",
+    "Press any key to close.",
+];
+
+fn draw_error_popup(f: &mut Frame, message: &str) {
+    let area = centered_rect(60, 30, f.size());
+    let popup = Paragraph::new(format!("{}\n\nPress any key to dismiss.", message)).wrap(Wrap { trim: true })
+        .style(Style::default().fg(Color::Red))
+        .block(Block::default().title("Error").borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+/// The message set by `set_status`, until it expires.
+fn draw_status_popup(f: &mut Frame, app: &App) {
+    if let Some((msg, _)) = &app.save_status_message {
+        let msg_p = Paragraph::new(msg.as_str()).wrap(Wrap { trim: true }).style(Style::default().fg(Color::Yellow));
+        let area = centered_rect(50, 20, f.size());
+        f.render_widget(Clear, area);
+        f.render_widget(msg_p.block(Block::default().borders(Borders::ALL).title("Status")), area);
+    }
+}
+
+fn draw_loading_popup(f: &mut Frame, group: &str) {
+    let area = centered_rect(50, 20, f.size());
+    let text = format!("Loading the members of '{}' from dnf...\n\n[Esc] Cancel", group);
+    let popup = Paragraph::new(text).wrap(Wrap { trim: true })
+        .block(Block::default().title("Group Info").borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+fn draw_info_popup(f: &mut Frame, title: &str, body: &str) {
+    let area = centered_rect(70, 70, f.size());
+    let popup = Paragraph::new(format!("{}\n\nPress any key to close.", body)).wrap(Wrap { trim: false })
+        .block(Block::default().title(title).borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+fn draw_detail_popup(f: &mut Frame, name: &str, details: &str, scroll: u16) {
+    let area = centered_rect(60, 50, f.size());
+    let popup = Paragraph::new(format!("{}\n\n[↑↓/PgUp/PgDn] Scroll | any other key closes", details))
+        .wrap(Wrap { trim: true })
+        .scroll((scroll, 0))
+        .block(Block::default().title(format!("Repository: {}", name)).borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+fn draw_report_popup(f: &mut Frame, report: &[(String, Vec<String>)], scroll: u16) {
+    let area = centered_rect(70, 80, f.size());
+    let mut lines = Vec::new();
+    for (title, entries) in report {
+        lines.push(Line::styled(title.clone(), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+        if entries.is_empty() {
+            lines.push(Line::styled("  (none)", Style::default().fg(Color::DarkGray)));
+        }
+        lines.extend(entries.iter().map(|entry| Line::raw(format!("  {}", entry))));
+        lines.push(Line::raw(""));
+    }
+    lines.push(Line::raw("[↑↓/PgUp/PgDn] Scroll | any other key closes"));
+    let popup = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0))
+        .block(Block::default().title("What Would Change").borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+fn draw_tag_popup(f: &mut Frame, tags: &[&str], filter: &BTreeSet<&str>, index: usize) {
+    let area = centered_rect(40, 40, f.size());
+    let rows: Vec<ListItem> = tags.iter()
+        .map(|tag| ListItem::new(format!("{} {}", if filter.contains(tag) { "[x]" } else { "[ ]" }, tag)))
+        .collect();
+    let list = List::new(rows)
+        .block(Block::default().title("Filter by Tag ([Space] Toggle, [Enter] Done)").borders(Borders::ALL))
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD).bg(Color::DarkGray))
+        .highlight_symbol(">> ");
+    let mut state = ratatui::widgets::ListState::default();
+    state.select(Some(index));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_help_popup(f: &mut Frame) {
+    let area = centered_rect(60, 60, f.size());
+    let popup = Paragraph::new(HELP_LINES.join("\n")).block(Block::default().title("Help").borders(Borders::ALL));
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
 /// Helper function to create a centered rectangle for popups
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default().direction(Direction::Vertical)
@@ -518,3 +3474,549 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         .constraints([Constraint::Percentage((100 - percent_x) / 2), Constraint::Percentage(percent_x), Constraint::Percentage((100 - percent_x) / 2)].as_ref())
         .split(popup_layout[1])[1]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const SELECTION: &[&str] = &[
+        "repo-ha", "repo-crb", "repo-epel", "copr-ptyxis",
+        "kvm-base", "app-ptyxis", "app-firefox-flatpak", "term-default-ptyxis",
+    ];
+
+    /// Whether any line of `text` calls sudo.
+    fn runs_sudo(text: &str) -> bool {
+        text.lines().any(|line| line.split_whitespace().any(|word| word == "sudo"))
+    }
+
+    /// Compares `actual` with the golden file `tests/snapshots/<name>.sh`.
+    /// Run with `UPDATE_SNAPSHOTS=1` to write the current output instead.
+    fn assert_snapshot(name: &str, actual: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots").join(format!("{}.sh", name));
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, actual).unwrap();
+            return;
+        }
+        let expected = fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("missing snapshot {} ({}); run with UPDATE_SNAPSHOTS=1", path.display(), err));
+        assert_eq!(expected, actual, "generated script differs from {}", path.display());
+    }
+
+    fn generate(os: OsDistribution, ids: &[&'static str], configure: impl FnOnce(&mut App), reboot: bool) -> String {
+        let mut app = App::for_target(os);
+        app.menu_tree.borrow_mut().select_ids(&ids.iter().copied().collect());
+        assert_eq!(app.menu_tree.borrow().count_selected(), ids.len(), "unknown item id in {:?}", ids);
+        configure(&mut app);
+        app.generate_commands(reboot)
+    }
+
+    #[test]
+    fn empty_selection() {
+        assert_snapshot("empty", &generate(OsDistribution::Rocky, &[], |_| {}, false));
+    }
+
+    #[test]
+    fn representative_selection_per_distro() {
+        for os in OsDistribution::TARGETS {
+            assert_snapshot(os.id(), &generate(os, SELECTION, |_| {}, false));
+        }
+    }
+
+    #[test]
+    fn generated_scripts_pass_bash_syntax_check() {
+        for os in OsDistribution::TARGETS {
+            let script = generate(os, SELECTION, |_| {}, true);
+            assert_eq!(runner::syntax_check(&script), Ok(Vec::new()), "{:?}", os);
+        }
+        let errors = runner::syntax_check("if true; then\necho unterminated").unwrap();
+        assert!(errors.iter().any(|line| line.starts_with("line ")), "{:?}", errors);
+    }
+
+    #[test]
+    fn multi_distro_bundle() {
+        let mut app = App::for_target(OsDistribution::Rocky);
+        app.menu_tree.borrow_mut().select_ids(&SELECTION.iter().copied().collect());
+        let script = app.generate_bundle(false);
+        assert_eq!(runner::syntax_check(&script), Ok(Vec::new()));
+        assert_eq!(app.os_distro, OsDistribution::Rocky);
+        assert_snapshot("bundle", &script);
+    }
+
+    #[test]
+    fn cloud_init() {
+        let app = App::for_target(OsDistribution::Rocky);
+        app.menu_tree.borrow_mut().select_ids(&SELECTION.iter().copied().collect());
+        let user_data = app.generate_cloud_init(true);
+        assert!(user_data.starts_with("#cloud-config\n") && !runs_sudo(&user_data.replace("\\n", "\n")), "{}", user_data);
+        assert_snapshot("rocky-cloud-init", &user_data);
+    }
+
+    #[test]
+    fn containerfile() {
+        let mut app = App::for_target(OsDistribution::Rocky);
+        app.os_version = Some(9);
+        app.menu_tree.borrow_mut().select_ids(&SELECTION.iter().copied().collect());
+        let containerfile = app.generate_containerfile();
+        assert_snapshot("rocky-9-containerfile", &containerfile);
+        // Base images have no sudo.
+        assert!(!runs_sudo(&containerfile), "{}", containerfile);
+    }
+
+    #[test]
+    fn generic_el_uses_config_manager() {
+        let script = generate(OsDistribution::GenericEl, &["repo-crb"], |_| {}, false);
+        assert!(script.contains("sudo dnf config-manager --set-enabled crb\n") && !script.contains("subscription-manager"), "{}", script);
+    }
+
+    #[test]
+    fn epel_brings_crb_once() {
+        let script = generate(OsDistribution::Rocky, &["repo-epel"], |_| {}, false);
+        assert!(script.contains("# + auto-added: Repositories > Add Repositories (ROCKY LINUX SPECIFIC) > CRB (code ready builder)\n"));
+        assert_eq!(script.matches("config-manager --set-enabled crb").count(), 1);
+        let script = generate(OsDistribution::Rhel, &["repo-crb", "repo-epel"], |_| {}, false);
+        assert_eq!(script.matches("--enable \"codeready-builder").count(), 1);
+    }
+
+    #[test]
+    fn dnf5_repo_enables() {
+        let script = generate(OsDistribution::Rocky, &["repo-ha", "repo-crb", "repo-epel"], |app| {
+            app.set_os_version(Some(10));
+        }, false);
+        assert_snapshot("rocky-10-dnf5", &script);
+    }
+
+    #[test]
+    fn split_per_category() {
+        let app = App::for_target(OsDistribution::Rocky);
+        app.menu_tree.borrow_mut().select_ids(&SELECTION.iter().copied().collect());
+        let scripts = app.generate_split(true);
+        assert_eq!(scripts.keys().copied().collect::<Vec<_>>(), ["packages", "repos"]);
+        for script in scripts.values() {
+            assert!(script.starts_with("#!/bin/bash\n"));
+        }
+        assert!(scripts["repos"].contains(runner::REPOS_SECTION) && !scripts["repos"].contains("APPLYING CONFIGURATIONS"));
+        assert!(!scripts["repos"].contains("sudo reboot"));
+        assert!(!scripts["packages"].contains(runner::REPOS_SECTION) && scripts["packages"].ends_with("sudo reboot\n"));
+    }
+
+    #[test]
+    fn reboot_over_ssh_is_scheduled_last() {
+        let script = generate(OsDistribution::Rocky, SELECTION, |app| app.over_ssh = true, true);
+        assert!(!script.contains("sudo reboot"));
+        assert!(script.ends_with("sudo systemd-run --on-active=10 --timer-property=AccuracySec=1s systemctl reboot\n"));
+    }
+
+    #[test]
+    fn profile_round_trip() {
+        let app = App::for_target(OsDistribution::Rocky);
+        app.menu_tree.borrow_mut().select_ids(&SELECTION.iter().copied().collect());
+        let profile = Profile::from_json(&app.current_profile().to_json()).unwrap();
+
+        let mut restored = App::for_target(OsDistribution::Rocky);
+        restored.apply_profile(&profile).unwrap();
+        assert_eq!(restored.generate_commands(false), app.generate_commands(false));
+    }
+
+    #[test]
+    fn default_repos_are_preselected() {
+        let app = App::with_menu_tree(OsDistribution::Rocky, scripts::build_menu_tree(OsDistribution::Rocky, Arch::default(), true));
+        let mut ids = HashSet::new();
+        app.menu_tree.borrow().selected_ids(&mut ids);
+        assert_eq!(ids, HashSet::from(["repo-crb", "repo-epel"]));
+        assert_eq!(scripts::build_menu_tree(OsDistribution::Unknown, Arch::default(), true).borrow().count_selected(), 0);
+    }
+
+    #[test]
+    fn group_excludes() {
+        let script = generate(OsDistribution::Rocky, &["gnome-full"], |app| {
+            app.group_excludes.insert("gnome-full", vec!["libreoffice*".to_string(), "cheese".to_string()]);
+        }, false);
+        assert!(script.contains("sudo dnf groupinstall -y 'Workstation' --exclude='libreoffice*,cheese'\n"));
+    }
+
+    #[test]
+    fn no_sudo_ascii_with_reboot() {
+        let script = generate(OsDistribution::Rocky, SELECTION, |app| {
+            app.no_sudo = true;
+            app.ascii = true;
+        }, true);
+        assert_snapshot("rocky-no-sudo-ascii-reboot", &script);
+    }
+
+    #[test]
+    fn next_item_skips_menus() {
+        let app = App::for_target(OsDistribution::Rocky);
+        let nodes = get_visible_nodes(&app);
+        let is_item = |index: usize| matches!(&*nodes[index].1.borrow(), MenuNode::Item { .. });
+        let items: Vec<usize> = (0..nodes.len()).filter(|&index| is_item(index)).collect();
+        assert!(items.len() > 1 && items.len() < nodes.len());
+        assert_eq!(next_item_index(&nodes, items[0], true, false), Some(items[1]));
+        assert_eq!(next_item_index(&nodes, items[0], false, false), None);
+        assert_eq!(next_item_index(&nodes, items[0], false, true), items.last().copied());
+    }
+
+    #[test]
+    fn prerequisites_can_be_kept_out() {
+        let script = generate(OsDistribution::Rocky, &["cockpit-machines"], |app| {
+            app.no_auto_include.insert("cockpit-base");
+        }, false);
+        assert!(script.contains("# NOTE: assumes cockpit-base is already installed"));
+        assert!(!script.contains("cockpit.socket"));
+        assert!(script.contains("sudo dnf install -y qemu-kvm"));
+    }
+
+    #[test]
+    fn restoring_a_session_keeps_the_tui_running() {
+        let mut app = App::for_target(OsDistribution::Rocky);
+        app.recovered = Some(Profile { items: vec!["kvm-base".to_string()], ..Profile::default() });
+        assert!(app.accept(Confirmation::RestoreSession).is_none());
+        assert_eq!(app.menu_tree.borrow().count_selected(), 1);
+        assert!(app.recovered.is_none() && app.recovery_due.is_none());
+    }
+
+    #[test]
+    fn stubs_are_flagged() {
+        let app = App::for_target(OsDistribution::Rocky);
+        let mut items = Vec::new();
+        app.menu_tree.borrow().collect_items(None, &mut items, true);
+        let stubs: Vec<&str> = items.iter().filter(|i| i.is_stub).map(|i| i.id).collect();
+        assert!(stubs.contains(&"sway-compile-1-10") && stubs.contains(&"gnome-ext-vitals"));
+        for item in items.iter().filter(|i| i.is_stub) {
+            let script = (item.script_fn)(&app.script_ctx);
+            assert!(script.lines().all(|line| line.starts_with("echo ")), "{} does real work", item.id);
+        }
+    }
+
+    #[test]
+    fn scheduled_reboot() {
+        let script = generate(OsDistribution::Rocky, &["kvm-base"], |app| app.reboot_at = Some("03:00".to_string()), true);
+        assert!(script.ends_with("sudo shutdown -r 03:00\n") && !script.contains("sudo reboot"));
+        assert_eq!(parse_reboot_time("3:05"), Ok("03:05".to_string()));
+        for bad in ["24:00", "12:60", "1200", "12:5", ":30", "ab:cd", "+1:00"] {
+            assert!(parse_reboot_time(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn exit_summary_lists_saved_scripts() {
+        let mut app = App::for_target(OsDistribution::Rocky);
+        app.menu_tree.borrow_mut().select_ids(&["kvm-base"].into_iter().collect());
+        assert!(app.exit_summary().to_string().ends_with("1 item selected.\nNo script was saved.\n"));
+        app.saved_paths.push(PathBuf::from("setup.sh"));
+        assert!(app.exit_summary().to_string().ends_with("1 item selected.\nSaved script: setup.sh\n"));
+    }
+
+    #[test]
+    fn shared_commands_run_once() {
+        let script = generate(OsDistribution::Rocky, &["cockpit-base", "cockpit-full"], |_| {}, false);
+        assert_eq!(script.matches("sudo systemctl enable --now cockpit.socket").count(), 1);
+        assert_eq!(script.matches("print_step \"Base Installation\"").count(), 1);
+        let mut seen = HashSet::new();
+        let first = "if true; then\n  echo hi\nfi\necho hi\n";
+        assert_eq!(dedupe_commands(first, &mut seen), first);
+        assert_eq!(dedupe_commands("print_step \"x\"\nif true; then\n  echo hi\nfi\necho hi\n", &mut seen), "print_step \"x\"\nif true; then\n  echo hi\nfi\n");
+    }
+
+    #[test]
+    fn schedules_must_be_one_line() {
+        assert!(runner::validate_schedule("  ").is_err());
+        assert!(runner::validate_schedule("weekly\nExecStart=/bin/true").is_err());
+    }
+
+    #[test]
+    fn tab_jumps_between_sections() {
+        let app = App::for_target(OsDistribution::Rocky);
+        let nodes = get_visible_nodes(&app);
+        let glyphs = app.glyphs();
+        let sections: Vec<usize> = (0..nodes.len()).filter(|&index| !nodes[index].0.starts_with(glyphs.pipe.trim_end())
+            && !nodes[index].0.starts_with(' ')).collect();
+        assert!(sections.len() > 2 && sections.len() < nodes.len());
+        assert_eq!(next_section_index(&nodes, sections[0], true, glyphs), Some(sections[1]));
+        assert_eq!(next_section_index(&nodes, sections[1] + 1, true, glyphs), Some(sections[2]));
+        assert_eq!(next_section_index(&nodes, sections[0], false, glyphs), sections.last().copied());
+    }
+
+    #[test]
+    fn search_flattens_matching_items() {
+        let mut app = App::for_target(OsDistribution::Rocky);
+        let tree_len = get_visible_nodes(&app).len();
+        app.search_input = TextInput::new("WAYBAR");
+        let nodes = get_visible_nodes(&app);
+        assert!(!nodes.is_empty() && nodes.len() < tree_len);
+        for (text, node) in &nodes {
+            assert!(matches!(&*node.borrow(), MenuNode::Item { .. }) && node.borrow().name().contains("Waybar"), "{}", text);
+            assert!(text.contains(" > "), "{}", text);
+        }
+        app.search_input.clear();
+        assert_eq!(get_visible_nodes(&app).len(), tree_len);
+    }
+
+    #[test]
+    fn yum_hosts() {
+        assert_eq!(PackageManager::detect(Some(7)), PackageManager::Yum);
+        let script = generate(OsDistribution::Centos, &["cockpit-base", "kvm-base"], |app| {
+            app.script_ctx.package_manager = PackageManager::Yum;
+            app.allow_erasing = true;
+        }, false);
+        assert!(script.contains("# Package manager: yum\n"));
+        assert!(script.contains("sudo yum install -y cockpit\n") && script.contains("sudo yum install -y qemu-kvm"));
+        assert!(!script.contains("--allowerasing"));
+
+        let ids = ["repo-epel", "repo-flathub", "copr-ptyxis", "container-rootless", "gnome-base", "sway-waybar", "app-ptyxis"];
+        let script = generate(OsDistribution::Centos, &ids, |app| app.script_ctx.package_manager = PackageManager::Yum, false);
+        assert!(script.contains("sudo yum install -y podman\n") && script.contains("sudo yum install -y flatpak\n"));
+        assert!(script.contains("rpm -q yum-utils yum-plugin-copr > /dev/null 2>&1 || sudo yum install -y yum-utils yum-plugin-copr\n"));
+        assert!(script.contains("sudo yum-config-manager --enable crb\n") && script.contains("sudo yum copr enable -y chergert/ptyxis\n"));
+        assert!(!script.contains("dnf "), "{}", script);
+    }
+
+    #[test]
+    fn group_installs_per_package_manager() {
+        let script = generate(OsDistribution::Centos, &["gnome-full"], |app| app.script_ctx.package_manager = PackageManager::Yum, false);
+        assert!(script.contains("sudo yum groupinstall -y 'Workstation'\n"));
+
+        // microdnf can run neither group installs, config-manager nor copr, so those items are unavailable.
+        let mut app = App::for_target(OsDistribution::Rocky);
+        app.script_ctx.package_manager = PackageManager::Microdnf;
+        scripts::mark_unsupported(&app.menu_tree, &app.script_ctx);
+        app.menu_tree.borrow_mut().select_ids(&["gnome-full", "repo-crb", "copr-ptyxis", "container-podman"].into_iter().collect());
+        assert_eq!(app.menu_tree.borrow().count_selected(), 1);
+        let script = app.generate_commands(false);
+        assert!(script.contains("sudo microdnf install -y podman\n") && !script.contains("groupinstall"), "{}", script);
+    }
+
+    #[test]
+    fn package_manager_follows_the_target() {
+        let mut app = App::for_target(OsDistribution::Rocky);
+        app.host_package_manager = PackageManager::Microdnf;
+        app.script_ctx.package_manager = PackageManager::Microdnf;
+        app.menu_tree.borrow_mut().select_ids(&["container-podman"].into_iter().collect());
+        app.output_format = OutputFormat::Containerfile;
+        assert!(app.script(false).contains("RUN dnf install -y podman\n"));
+        assert_eq!(app.script_ctx.package_manager, PackageManager::Microdnf);
+        app.rebuild_for(OsDistribution::AlmaLinux);
+        assert_eq!(app.script_ctx.package_manager, PackageManager::Dnf);
+        app.rebuild_for(OsDistribution::Rocky);
+        assert_eq!(app.script_ctx.package_manager, PackageManager::Microdnf);
+    }
+
+    #[test]
+    fn invert_only_touches_the_rows_in_view() {
+        let mut app = App::for_target(OsDistribution::Rocky);
+        app.search_input = TextInput::new("WAYBAR");
+        let visible = get_visible_nodes(&app);
+        app.invert_selection(&visible);
+        let mut selected = HashSet::new();
+        app.menu_tree.borrow().selected_ids(&mut selected);
+        assert_eq!(selected, HashSet::from(["sway-waybar"]));
+        assert!(app.history.last_used("sway-waybar").is_some());
+
+        app.search_input.clear();
+        let virt = app.menu_tree.borrow().children().unwrap().iter().find(|child| child.borrow().name() == "Virtualization").unwrap().clone();
+        app.nav_path.push(virt.clone());
+        let visible = get_visible_nodes(&app);
+        app.invert_selection(&visible);
+        let mut items = Vec::new();
+        virt.borrow().collect_items(None, &mut items, false);
+        assert!(items.len() > 1 && items.iter().all(|item| app.history.last_used(item.id).is_some()));
+        assert_eq!(app.menu_tree.borrow().count_selected(), items.len() + 1);
+    }
+
+    #[test]
+    fn subtree_selection_is_idempotent() {
+        let mut app = App::for_target(OsDistribution::Rocky);
+        let tree = app.menu_tree.clone();
+        let virt = tree.borrow().children().unwrap().iter().find(|child| child.borrow().name() == "Virtualization").unwrap().clone();
+        let selected = |node: &Rc<RefCell<MenuNode>>| {
+            let mut items = Vec::new();
+            node.borrow().collect_items(None, &mut items, false);
+            items
+        };
+        app.set_subtree_selection(&tree, false);
+        app.set_subtree_selection(&virt, true);
+        let items = selected(&virt);
+        assert!(items.len() > 1);
+        assert!(items.iter().all(|item| app.history.last_used(item.id).is_some()));
+        app.set_subtree_selection(&virt, true);
+        assert_eq!(selected(&virt).len(), items.len());
+        assert_eq!(selected(&tree).len(), items.len());
+        app.set_subtree_selection(&virt, false);
+        app.set_subtree_selection(&virt, false);
+        assert!(selected(&tree).is_empty());
+    }
+
+    #[test]
+    fn status_messages_show_on_the_menu_screen() {
+        let mut app = App::for_target(OsDistribution::Rocky);
+        app.set_status("Copied the command for Cockpit".to_string());
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(120, 40)).unwrap();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        let screen: String = terminal.backend().buffer().content.iter().map(|cell| cell.symbol()).collect();
+        assert!(screen.contains("Copied the command for Cockpit"));
+    }
+
+    #[test]
+    fn epel_release_follows_the_detected_version() {
+        let mut app = App::for_target(OsDistribution::Rocky);
+        app.set_os_version(Some(8));
+        app.menu_tree.borrow_mut().select_ids(&["repo-epel"].into_iter().collect());
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(120, 40)).unwrap();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        let screen: String = terminal.backend().buffer().content.iter().map(|cell| cell.symbol()).collect();
+        assert!(screen.contains("Detected: Rocky 8"), "{}", screen);
+        assert!(app.generate_commands(false).contains("epel-release-latest-8.noarch.rpm"));
+
+        // Another distro's version is not known here.
+        app.rebuild_for(OsDistribution::AlmaLinux);
+        assert!(app.generate_commands(false).contains(&format!("epel-release-latest-{}.noarch.rpm", scripts::FALLBACK_EL_VERSION)));
+        app.rebuild_for(OsDistribution::Rocky);
+        assert!(app.generate_commands(false).contains("epel-release-latest-8.noarch.rpm"));
+
+        app.script_ctx.epel_version = 10;
+        app.rebuild_for(OsDistribution::AlmaLinux);
+        assert_eq!(app.script_ctx.epel_version, 10);
+
+        app.rebuild_for(OsDistribution::Rocky);
+        app.set_os_version(None);
+        assert_eq!(app.script_ctx.epel_version, scripts::FALLBACK_EL_VERSION);
+    }
+
+    #[test]
+    fn review_footer_fits_and_lists_profile_saving() {
+        let mut app = App::for_target(OsDistribution::Rocky);
+        app.state = AppState::Finished;
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        let screen: String = terminal.backend().buffer().content.iter().map(|cell| cell.symbol()).collect();
+        assert!(screen.contains("[p] Save Profile") && screen.contains("[?] All Keys"), "{}", screen);
+    }
+
+    #[test]
+    fn install_flags() {
+        let script = generate(OsDistribution::Rocky, &["cockpit-base", "gnome-full"], |app| {
+            app.allow_erasing = true;
+            app.nobest = true;
+        }, false);
+        assert!(script.contains("sudo dnf install --allowerasing --nobest -y cockpit\n"));
+        assert!(script.contains("dnf groupinstall --allowerasing --nobest -y"));
+        assert!(!generate(OsDistribution::Rocky, &["cockpit-base"], |_| {}, false).contains("--allowerasing"));
+    }
+
+    #[test]
+    fn change_report_groups() {
+        let mut app = App::for_target(OsDistribution::Rocky);
+        app.detected_os = OsDistribution::Rocky;
+        app.enabled_repos.insert("crb".to_string());
+        app.menu_tree.borrow_mut().select_ids(&["repo-crb", "cockpit-base", "gnome-full"].into_iter().collect());
+        let report = app.change_report(|names| {
+            assert!(names.contains(&"cockpit".to_string()));
+            Ok(["cockpit".to_string()].into_iter().collect())
+        });
+        let group = |prefix: &str| &report.iter().find(|(title, _)| title.starts_with(prefix)).unwrap().1;
+        assert_eq!(group("Repositories already enabled"), &vec!["crb".to_string()]);
+        assert_eq!(group("Packages already installed"), &vec!["cockpit".to_string()]);
+        assert!(!group("Packages to install").contains(&"cockpit".to_string()));
+        assert_eq!(group("Package groups").len(), 1);
+    }
+
+    #[test]
+    fn recorded_runs() {
+        let script = generate(OsDistribution::Rocky, SELECTION, |app| app.script_ctx.record_run = true, false);
+        assert_snapshot("rocky-record-run", &script);
+        let plain = generate(OsDistribution::Rocky, SELECTION, |_| {}, false);
+        assert!(!plain.contains("record_step"));
+    }
+
+    #[test]
+    fn proxy() {
+        let script = generate(OsDistribution::Rocky, SELECTION, |app| {
+            app.script_ctx.proxy = Some("http://proxy.example.com:3128".to_string());
+        }, false);
+        assert_snapshot("rocky-proxy", &script);
+        for bad in ["proxy:3128", "ftp://proxy", "http://", "http://user@", "http://pro xy", "http://a'b"] {
+            assert!(scripts::validate_proxy(bad).is_err(), "{}", bad);
+        }
+        assert!(scripts::validate_proxy(" socks5h://user:pw@10.0.0.1:1080 ").is_ok());
+    }
+
+    #[test]
+    fn profiles_diff_by_category() {
+        let tree = scripts::build_menu_tree(OsDistribution::Rocky, Arch::default(), false);
+        let profile = |ids: &[&str]| Profile { items: ids.iter().map(|id| id.to_string()).collect(), ..Profile::default() };
+        let web = profile(&["cockpit-base", "repo-epel", "repo-crb", "retired-item"]);
+        let kvm = profile(&["kvm-base", "repo-crb", "repo-epel"]);
+        let (text, differ) = profile_diff(&tree.borrow(), ("web.json", &web), ("kvm.json", &kvm));
+        assert!(differ);
+        assert_eq!(text, "Only in web.json:\n  Configuration:\n    cockpit-base\n  Unknown ids:\n    retired-item\n\
+            Only in kvm.json:\n  Configuration:\n    kvm-base\n");
+        assert!(!profile_diff(&tree.borrow(), ("a", &kvm), ("b", &kvm)).1);
+    }
+
+    #[test]
+    fn toml_profiles_select_by_qualified_name() {
+        let mut app = App::for_target(OsDistribution::Rocky);
+        let selected = |app: &App| {
+            let mut ids = HashSet::new();
+            app.menu_tree.borrow().selected_ids(&mut ids);
+            ids
+        };
+        // "Base Installation" is the leaf name of several unrelated items.
+        let problems = app.apply_toml_profile(&["Base Installation".to_string(), "No Such Item".to_string()]);
+        assert!(problems[0].starts_with("Base Installation: matches") && problems[0].contains("Virtualization > "), "{}", problems[0]);
+        assert_eq!(problems[1], "No Such Item: no such item");
+        assert!(selected(&app).is_empty());
+
+        let mut items = Vec::new();
+        item_nodes(&app.menu_tree, None, &mut items);
+        let qualified = |wanted: &str| items.iter()
+            .find(|(_, node)| matches!(&*node.borrow(), MenuNode::Item { id, .. } if *id == wanted))
+            .unwrap().0.clone();
+        let profile = TomlProfile { items: vec![qualified("kvm-base"), qualified("term-default-ptyxis"), qualified("term-default-konsole")] };
+        let profile: TomlProfile = toml::from_str(&toml::to_string(&profile).unwrap()).unwrap();
+        assert!(app.apply_toml_profile(&profile.items).is_empty());
+        // The later radio option wins, as it would when toggled by hand.
+        assert_eq!(selected(&app), HashSet::from(["kvm-base", "term-default-konsole"]));
+    }
+
+    #[test]
+    fn root_scripts_skip_sudo() {
+        let script = generate(OsDistribution::Rocky, SELECTION, |app| app.privilege = Privilege::Root, true);
+        assert_snapshot("rocky-as-root", &script);
+        assert_eq!(runner::strip_sudo("sudo dnf install -y a | sudo tee x\nsudo -u \"$TARGET_USER\" true\n# needs sudo here\npseudo sudo\n"),
+            "dnf install -y a | tee x\nsudo -u \"$TARGET_USER\" true\n# needs sudo here\npseudo sudo\n");
+    }
+
+    #[test]
+    fn tag_filter_spans_menus() {
+        let mut app = App::for_target(OsDistribution::Rocky);
+        assert!(app.all_tags().contains(&"experimental"));
+        app.tag_filter.insert("experimental");
+        let nodes = get_visible_nodes(&app);
+        let ids: Vec<&str> = nodes.iter().filter_map(|(_, node)| match &*node.borrow() {
+            MenuNode::Item { id, meta, .. } => Some(*id).filter(|_| meta.tags.contains(&"experimental")),
+            _ => None,
+        }).collect();
+        assert_eq!(ids.len(), nodes.len());
+        assert!(ids.contains(&"install-xen") && ids.contains(&"sway-compile-1-10") && ids.contains(&"repo-devel"));
+    }
+
+    #[test]
+    fn group_members_replace_group_install() {
+        let script = generate(OsDistribution::Rocky, &["gnome-full"], |app| {
+            app.group_members.insert("gnome-full", vec!["gnome-shell".to_string(), "@Fonts".to_string()]);
+        }, false);
+        assert!(script.contains("sudo dnf install -y 'gnome-shell' '@Fonts'\n") && !script.contains("groupinstall"));
+    }
+
+    #[test]
+    fn kvm_firmware_follows_arch() {
+        for (arch, firmware) in [(Arch::X86_64, "edk2-ovmf"), (Arch::Aarch64, "edk2-aarch64")] {
+            let app = App::with_menu_tree(OsDistribution::Rocky, scripts::build_menu_tree(OsDistribution::Rocky, arch, false));
+            app.menu_tree.borrow_mut().select_ids(&HashSet::from(["kvm-base"]));
+            let script = app.generate_commands(false);
+            assert!(script.contains(firmware), "{:?} script lacks {}:\n{}", arch, firmware, script);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+// src/members.rs
+
+use crate::dnf::GroupSection;
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+/// One package or sub-group of a group install, as an install spec ("@Name" for groups).
+struct Member {
+    spec: String,
+    /// Whether a plain group install would include it (mandatory and default members).
+    default: bool,
+    chosen: bool,
+}
+
+/// Popup that expands a group-install item into its members so a subset can be installed.
+pub struct MemberPicker {
+    pub item_id: &'static str,
+    group: String,
+    members: Vec<Member>,
+    index: usize,
+}
+
+impl MemberPicker {
+    /// Creates a picker from a `dnf group info` listing. `current` is the subset chosen
+    /// earlier; without one, the members a group install would pull in are checked.
+    pub fn new(item_id: &'static str, group: &str, sections: &[GroupSection], current: Option<&Vec<String>>) -> MemberPicker {
+        let mut members: Vec<Member> = Vec::new();
+        for section in sections {
+            let title = section.title.to_lowercase();
+            let default = !title.contains("optional");
+            for name in &section.members {
+                let spec = if title.contains("group") { format!("@{}", name) } else { name.clone() };
+                if members.iter().any(|member| member.spec == spec) {
+                    continue;
+                }
+                let chosen = current.map_or(default, |current| current.contains(&spec));
+                members.push(Member { spec, default, chosen });
+            }
+        }
+        MemberPicker { item_id, group: group.to_string(), members, index: 0 }
+    }
+
+    /// Up and Down move, Space toggles the highlighted member.
+    pub fn handle_key(&mut self, code: KeyCode) {
+        let len = self.members.len();
+        match code {
+            KeyCode::Down if len > 0 => self.index = (self.index + 1) % len,
+            KeyCode::Up if len > 0 => self.index = (self.index + len - 1) % len,
+            KeyCode::Char(' ') => {
+                if let Some(member) = self.members.get_mut(self.index) {
+                    member.chosen = !member.chosen;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The chosen members, or `None` when they are exactly what the group install would
+    /// pull in, so the plain group install can stay.
+    pub fn chosen(&self) -> Option<Vec<String>> {
+        if self.members.iter().all(|member| member.chosen == member.default) {
+            return None;
+        }
+        Some(self.members.iter().filter(|member| member.chosen).map(|member| member.spec.clone()).collect())
+    }
+}
+
+pub fn draw_member_popup(f: &mut Frame, picker: &MemberPicker, area: Rect) {
+    let rows: Vec<ListItem> = picker.members.iter().map(|member| {
+        let optional = if member.default { "" } else { "  (optional)" };
+        ListItem::new(format!("{} {}{}", if member.chosen { "[x]" } else { "[ ]" }, member.spec, optional))
+    }).collect();
+    let title = format!("Members of {} ([Space] Toggle, [Enter] Apply, [Esc] Cancel)", picker.group);
+    let list = List::new(rows)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD).bg(Color::DarkGray))
+        .highlight_symbol(">> ");
+    let mut state = ListState::default();
+    state.select(Some(picker.index));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
@@ -0,0 +1,127 @@
+// src/os_release.rs
+
+use crate::OsDistribution;
+use std::{collections::HashMap, fs};
+
+/// The `KEY=value` fields of an os-release file.
+#[derive(Debug, Default)]
+pub struct OsRelease {
+    fields: HashMap<String, String>,
+}
+
+impl OsRelease {
+    /// Reads /etc/os-release, falling back to /usr/lib/os-release as os-release(5) specifies.
+    pub fn load() -> Option<OsRelease> {
+        ["/etc/os-release", "/usr/lib/os-release"].iter()
+            .find_map(|path| fs::read_to_string(path).ok())
+            .map(|content| OsRelease::parse(&content))
+    }
+
+    /// Parses os-release content. Values may be double-quoted, single-quoted or bare; blank
+    /// lines, comments and lines without `=` are skipped.
+    pub fn parse(content: &str) -> OsRelease {
+        let fields = content.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), unquote(value.trim())))
+            .collect();
+        OsRelease { fields }
+    }
+
+    pub fn field(&self, key: &str) -> Option<&str> {
+        self.fields.get(key).map(String::as_str)
+    }
+
+    /// The EL major version. `PLATFORM_ID` ("platform:el9" -> 9) is authoritative when present,
+    /// since rebuilds number `VERSION_ID` their own way; otherwise `VERSION_ID`, e.g. "9.3" -> 9.
+    pub fn major_version(&self) -> Option<u32> {
+        self.platform_version()
+            .or_else(|| self.field("VERSION_ID")?.split('.').next()?.trim().parse().ok())
+    }
+
+    /// The major version named by an EL `PLATFORM_ID`, e.g. "platform:el10" -> 10.
+    fn platform_version(&self) -> Option<u32> {
+        self.field("PLATFORM_ID")?.strip_prefix("platform:el")?.parse().ok()
+    }
+
+    /// The distro these fields describe.
+    pub fn distribution(&self) -> OsDistribution {
+        match self.field("ID") {
+            Some("rhel") => OsDistribution::Rhel,
+            // CentOS Stream shares ID=centos with CentOS Linux; only NAME tells them apart.
+            Some("centos") if self.field("NAME").is_some_and(|name| name.contains("Stream")) => OsDistribution::CentosStream,
+            Some("centos") => OsDistribution::Centos,
+            Some("rocky") => OsDistribution::Rocky,
+            Some("almalinux") => OsDistribution::AlmaLinux,
+            Some(_) if self.field("ID_LIKE").is_some_and(|like| like.split_whitespace().any(|id| id == "rhel")) => OsDistribution::GenericEl,
+            // Rebuilds that leave out ID_LIKE still declare the EL platform they target.
+            Some(_) if self.platform_version().is_some() => OsDistribution::GenericEl,
+            _ => OsDistribution::Unknown,
+        }
+    }
+}
+
+/// Strips matching surrounding quotes. Inside double quotes, backslash escapes a character.
+fn unquote(value: &str) -> String {
+    if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        return value[1..value.len() - 1].to_string();
+    }
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        let mut unquoted = String::new();
+        let mut chars = value[1..value.len() - 1].chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => unquoted.extend(chars.next()),
+                c => unquoted.push(c),
+            }
+        }
+        return unquoted;
+    }
+    value.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_and_bare_values() {
+        let release = OsRelease::parse("# comment\nID=rocky\nNAME='Rocky Linux'\nPRETTY_NAME=\"Rocky \\\"Blue Onyx\\\"\"\n\nVERSION_ID=\"9.3\"\n");
+        assert_eq!(release.field("ID"), Some("rocky"));
+        assert_eq!(release.field("NAME"), Some("Rocky Linux"));
+        assert_eq!(release.field("PRETTY_NAME"), Some("Rocky \"Blue Onyx\""));
+        assert_eq!(release.distribution(), OsDistribution::Rocky);
+    }
+
+    #[test]
+    fn major_version_formats() {
+        for (line, major) in [("VERSION_ID=\"9.3\"", Some(9)), ("VERSION_ID=10", Some(10)), ("VERSION_ID='8.10'", Some(8)), ("VERSION_ID=\"\"", None)] {
+            assert_eq!(OsRelease::parse(line).major_version(), major, "{}", line);
+        }
+        assert_eq!(OsRelease::parse("ID=rhel").major_version(), None);
+    }
+
+    #[test]
+    fn platform_id_wins_over_version_id() {
+        let release = OsRelease::parse("ID=\"myel\"\nVERSION_ID=\"2024.1\"\nPLATFORM_ID=\"platform:el9\"");
+        assert_eq!(release.major_version(), Some(9));
+        assert_eq!(release.distribution(), OsDistribution::GenericEl);
+        assert_eq!(OsRelease::parse("PLATFORM_ID=\"platform:f40\"\nVERSION_ID=40").major_version(), Some(40));
+        assert_eq!(OsRelease::parse("ID=fedora\nPLATFORM_ID=\"platform:f40\"").distribution(), OsDistribution::Unknown);
+    }
+
+    #[test]
+    fn distributions() {
+        let cases = [
+            ("ID=\"centos\"\nNAME=\"CentOS Stream\"", OsDistribution::CentosStream),
+            ("ID='centos'\nNAME='CentOS Linux'", OsDistribution::Centos),
+            ("ID=ol\nID_LIKE=\"fedora rhel\"", OsDistribution::GenericEl),
+            ("ID=debian", OsDistribution::Unknown),
+            ("", OsDistribution::Unknown),
+        ];
+        for (content, os) in cases {
+            assert_eq!(OsRelease::parse(content).distribution(), os, "{}", content);
+        }
+    }
+}
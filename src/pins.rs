@@ -0,0 +1,116 @@
+// src/pins.rs
+
+use crate::input::TextInput;
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+/// Package versions pinned for one item, optionally held with `dnf versionlock`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionPin {
+    /// `(package, version)` pairs, e.g. `("podman", "4.9.4")`.
+    pub packages: Vec<(String, String)>,
+    pub lock: bool,
+}
+
+impl VersionPin {
+    /// Parses space-separated `name=version` specs.
+    pub fn parse(specs: &str, lock: bool) -> Result<VersionPin, String> {
+        let mut packages = Vec::new();
+        for spec in specs.split_whitespace() {
+            match spec.split_once('=') {
+                Some((name, version)) if !name.is_empty() && !version.is_empty() => {
+                    packages.push((name.to_string(), version.to_string()));
+                }
+                _ => return Err(format!("Expected name=version, got '{}'", spec)),
+            }
+        }
+        Ok(VersionPin { packages, lock })
+    }
+
+    /// The specs in the form accepted by `parse`.
+    pub fn specs(&self) -> String {
+        self.packages.iter().map(|(name, version)| format!("{}={}", name, version)).collect::<Vec<_>>().join(" ")
+    }
+
+    /// Rewrites the pinned packages on `dnf install` lines to `name-version`, then adds
+    /// versionlock entries when requested.
+    pub fn apply(&self, script: &str) -> String {
+        let mut lines: Vec<String> = script.lines().map(|line| {
+            if !line.contains("dnf install") {
+                return line.to_string();
+            }
+            line.split(' ').map(|word| match self.packages.iter().find(|(name, _)| name == word) {
+                Some((name, version)) => format!("{}-{}", name, version),
+                None => word.to_string(),
+            }).collect::<Vec<_>>().join(" ")
+        }).collect();
+
+        if self.lock && !self.packages.is_empty() {
+            let locked: Vec<String> = self.packages.iter().map(|(name, version)| format!("{}-{}", name, version)).collect();
+            lines.push("sudo dnf install -y python3-dnf-plugin-versionlock".to_string());
+            lines.push(format!("sudo dnf versionlock add {}", locked.join(" ")));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Popup for editing the version pins of one selected item.
+pub struct PinForm {
+    pub item_id: &'static str,
+    item_name: String,
+    input: TextInput,
+    lock: bool,
+    pub error: Option<String>,
+}
+
+impl PinForm {
+    /// Creates a form for `item_id`, pre-filled with its current pin if it has one.
+    pub fn new(item_id: &'static str, item_name: &str, current: Option<&VersionPin>) -> PinForm {
+        PinForm {
+            item_id,
+            item_name: item_name.to_string(),
+            input: TextInput::new(current.map(VersionPin::specs).unwrap_or_default()),
+            lock: current.is_some_and(|pin| pin.lock),
+            error: None,
+        }
+    }
+
+    /// Tab toggles versionlock; other keys edit the specs.
+    pub fn handle_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Tab => self.lock = !self.lock,
+            code => { self.input.handle_key(code); }
+        }
+    }
+
+    /// Parses the form. `Ok(None)` means the pins were cleared.
+    pub fn pin(&self) -> Result<Option<VersionPin>, String> {
+        let pin = VersionPin::parse(&self.input.value, self.lock)?;
+        Ok(if pin.packages.is_empty() { None } else { Some(pin) })
+    }
+}
+
+pub fn draw_pin_popup(f: &mut Frame, form: &PinForm, area: Rect) {
+    let block = Block::default().title(format!("Version Pins: {}", form.item_name)).borders(Borders::ALL);
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default().direction(Direction::Vertical).margin(2)
+        .constraints([Constraint::Length(3), Constraint::Length(1), Constraint::Length(1), Constraint::Min(1)].as_ref())
+        .split(area);
+
+    f.render_widget(form.input.widget("Packages (name=version, space-separated)", true), chunks[0]);
+    let lock = if form.lock { "on" } else { "off" };
+    f.render_widget(Paragraph::new(format!("Versionlock after install: {}", lock)), chunks[1]);
+
+    let hint = match &form.error {
+        Some(err) => Paragraph::new(err.as_str()).style(Style::default().fg(Color::Red)),
+        None => Paragraph::new("[Tab] Toggle versionlock | [Enter] Apply (empty clears) | [Esc] Cancel"),
+    };
+    f.render_widget(hint, chunks[2]);
+}
@@ -0,0 +1,200 @@
+// src/profile.rs
+
+use crate::input::TextInput;
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}};
+
+/// A saved selection: item ids plus the per-item notes and run options that go with them.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Profile {
+    /// Stable ids of the selected items, e.g. "kvm-base".
+    pub items: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub notes: BTreeMap<String, String>,
+    #[serde(default)]
+    pub reboot: bool,
+    /// Time of day ("HH:MM") the reboot is scheduled for instead of right after the script.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reboot_at: Option<String>,
+    #[serde(default)]
+    pub no_sudo: bool,
+}
+
+impl Profile {
+    pub fn from_json(text: &str) -> Result<Profile, String> {
+        serde_json::from_str(text).map_err(|err| format!("invalid profile: {}", err))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Loads the named profile from the profiles directory.
+    pub fn load(name: &str) -> Result<Profile, String> {
+        let path = path_for(name)?;
+        let text = fs::read_to_string(&path).map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+        Profile::from_json(&text)
+    }
+
+    /// Writes the profile under `name`, replacing any profile of that name.
+    pub fn save(&self, name: &str) -> Result<PathBuf, String> {
+        let path = path_for(name)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| format!("Failed to create {}: {}", dir.display(), err))?;
+        }
+        fs::write(&path, self.to_json()).map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+        Ok(path)
+    }
+}
+
+/// A selection kept by item name in the TOML file given with `--profile`, for reuse on other
+/// machines. Names may be qualified with their menu path ("Sway WM > Waybar") where a plain
+/// name is ambiguous; the review screen's [p] always writes qualified names.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TomlProfile {
+    pub items: Vec<String>,
+}
+
+impl TomlProfile {
+    pub fn read(path: &Path) -> Result<TomlProfile, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+        toml::from_str(&text).map_err(|err| format!("invalid profile {}: {}", path.display(), err))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let text = toml::to_string(self).map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| format!("Failed to write {}: {}", path.display(), err))
+    }
+}
+
+/// `~/.config/el-init/profiles`, or `None` without a home or config directory.
+fn profiles_dir() -> Option<PathBuf> {
+    crate::config_dir().map(|dir| dir.join("profiles"))
+}
+
+/// The file for a profile name. Names are limited to one plain path component.
+fn path_for(name: &str) -> Result<PathBuf, String> {
+    let valid = !name.is_empty() && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(format!("Invalid profile name '{}': use letters, digits, '-', '_' and '.'", name));
+    }
+    let dir = profiles_dir().ok_or("No config directory (HOME and XDG_CONFIG_HOME are unset)")?;
+    Ok(dir.join(format!("{}.json", name)))
+}
+
+/// Names of the saved profiles, sorted.
+pub fn list() -> Vec<String> {
+    let Some(entries) = profiles_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            (path.extension()? == "json").then(|| path.file_stem()?.to_str().map(str::to_string))?
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+fn delete(name: &str) -> Result<(), String> {
+    let path = path_for(name)?;
+    fs::remove_file(&path).map_err(|err| format!("Failed to delete {}: {}", path.display(), err))
+}
+
+/// What the profiles popup asks the app to do.
+pub enum ProfileAction {
+    Load(String),
+    Save(String),
+    Close,
+}
+
+/// Popup listing the saved profiles. The first row saves the current selection under a new name.
+pub struct ProfileManager {
+    names: Vec<String>,
+    index: usize,
+    // Set while the name for a new profile is being typed.
+    naming: Option<TextInput>,
+    pub error: Option<String>,
+}
+
+impl ProfileManager {
+    pub fn open() -> ProfileManager {
+        ProfileManager { names: list(), index: 0, naming: None, error: None }
+    }
+
+    /// Re-reads the profiles directory, keeping the cursor in range.
+    fn refresh(&mut self) {
+        self.names = list();
+        self.index = self.index.min(self.names.len());
+    }
+
+    /// Enter loads the highlighted profile (or starts naming a new one), `d` deletes it.
+    pub fn handle_key(&mut self, code: KeyCode) -> Option<ProfileAction> {
+        self.error = None;
+        if let Some(input) = self.naming.as_mut() {
+            match code {
+                KeyCode::Esc => self.naming = None,
+                KeyCode::Enter => {
+                    let name = input.value.trim().to_string();
+                    self.naming = None;
+                    return Some(ProfileAction::Save(name));
+                }
+                code => { input.handle_key(code); }
+            }
+            return None;
+        }
+        match code {
+            KeyCode::Esc | KeyCode::Char('q') => return Some(ProfileAction::Close),
+            KeyCode::Up => self.index = self.index.saturating_sub(1),
+            KeyCode::Down => self.index = (self.index + 1).min(self.names.len()),
+            KeyCode::Enter if self.index == 0 => self.naming = Some(TextInput::default()),
+            KeyCode::Enter => return Some(ProfileAction::Load(self.names[self.index - 1].clone())),
+            KeyCode::Char('d') if self.index > 0 => {
+                if let Err(err) = delete(&self.names[self.index - 1]) {
+                    self.error = Some(err);
+                }
+                self.refresh();
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+pub fn draw_profiles_popup(f: &mut Frame, manager: &ProfileManager, area: Rect) {
+    let block = Block::default().title("Profiles").borders(Borders::ALL);
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default().direction(Direction::Vertical).margin(1)
+        .constraints([Constraint::Min(1), Constraint::Length(3), Constraint::Length(1)].as_ref())
+        .split(area);
+
+    let rows: Vec<ListItem> = std::iter::once(ListItem::new("+ Save current selection as..."))
+        .chain(manager.names.iter().map(|name| ListItem::new(name.as_str())))
+        .collect();
+    let list = List::new(rows)
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD).bg(Color::DarkGray))
+        .highlight_symbol(">> ");
+    let mut state = ListState::default();
+    state.select(Some(manager.index));
+    f.render_stateful_widget(list, chunks[0], &mut state);
+
+    if let Some(input) = &manager.naming {
+        f.render_widget(input.widget("Profile name (Enter saves, Esc cancels)", true), chunks[1]);
+    }
+    let hint = match &manager.error {
+        Some(err) => Paragraph::new(err.as_str()).style(Style::default().fg(Color::Red)),
+        None => Paragraph::new("[Enter] Load / Save | [d] Delete | [Esc] Close"),
+    };
+    f.render_widget(hint, chunks[2]);
+}
@@ -0,0 +1,59 @@
+// src/recovery.rs
+
+use crate::profile::Profile;
+use crossterm::{
+    event::DisableMouseCapture,
+    execute,
+    terminal::{disable_raw_mode, LeaveAlternateScreen},
+};
+use std::{fs, io, panic, path::PathBuf, sync::Mutex};
+
+// The latest selection snapshot, kept in memory so the panic hook can write it out.
+static SNAPSHOT: Mutex<Option<String>> = Mutex::new(None);
+
+/// `~/.config/el-init/.crash-recovery`, or `None` without a home or config directory.
+fn path() -> Option<PathBuf> {
+    crate::config_dir().map(|dir| dir.join(".crash-recovery"))
+}
+
+/// Remembers the current selection; `flush` writes it to the recovery file.
+pub fn record(profile: &Profile) {
+    if let Ok(mut snapshot) = SNAPSHOT.lock() {
+        *snapshot = Some(profile.to_json());
+    }
+}
+
+/// Writes the last recorded snapshot. Errors are ignored: recovery is best effort.
+pub fn flush() {
+    let Some(path) = path() else { return };
+    let Ok(snapshot) = SNAPSHOT.lock() else { return };
+    if let Some(text) = snapshot.as_ref() {
+        let _ = path.parent().map(fs::create_dir_all);
+        let _ = fs::write(&path, text);
+    }
+}
+
+/// The session saved by a run that did not exit cleanly, if there is one.
+pub fn load() -> Option<Profile> {
+    let text = fs::read_to_string(path()?).ok()?;
+    Profile::from_json(&text).ok()
+}
+
+/// Removes the recovery file; called on a clean exit.
+pub fn clear() {
+    if let Some(path) = path() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Saves the selection and restores the terminal before the default panic message, so the
+/// message is readable and the next launch can offer to restore the session.
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        flush();
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture);
+        default_hook(info);
+    }));
+}
@@ -0,0 +1,429 @@
+// src/runner.rs
+
+use std::{env, fs, io::{self, BufRead, Write}, path::{Path, PathBuf}, process::{Command, Stdio}};
+
+/// Header comment that opens the repository section of a generated script.
+pub const REPOS_SECTION: &str = "# --- 1. ENABLING REPOSITORIES ---";
+/// Marker emitted after the repository section; two-phase runs split the script here.
+pub const PHASE_BOUNDARY: &str = "# --- END OF REPOSITORY PHASE ---";
+
+/// Where the first-boot script and its unit are installed.
+pub const FIRSTBOOT_SCRIPT: &str = "/usr/local/sbin/el-init-firstboot.sh";
+pub const FIRSTBOOT_UNIT: &str = "/etc/systemd/system/el-init-firstboot.service";
+
+/// Where a scheduled script and its service and timer are installed.
+pub const SCHEDULED_SCRIPT: &str = "/usr/local/sbin/el-init-scheduled.sh";
+pub const SCHEDULED_SERVICE: &str = "/etc/systemd/system/el-init-scheduled.service";
+pub const SCHEDULED_TIMER: &str = "/etc/systemd/system/el-init-scheduled.timer";
+
+/// How the invoking user gets root for the generated commands.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Privilege {
+    /// Already root: `sudo` prefixes are redundant.
+    Root,
+    #[default]
+    Sudo,
+    /// Not root and no `sudo` on the PATH, so running the script will fail.
+    NoSudo,
+}
+
+impl Privilege {
+    /// The privilege of this process, from its effective uid and whether `sudo` can be started.
+    pub fn detect() -> Privilege {
+        let status = fs::read_to_string("/proc/self/status").unwrap_or_default();
+        if effective_uid(&status) == Some(0) {
+            return Privilege::Root;
+        }
+        let sudo = Command::new("sudo").arg("-V").stdout(Stdio::null()).stderr(Stdio::null()).status();
+        if sudo.is_ok_and(|status| status.success()) { Privilege::Sudo } else { Privilege::NoSudo }
+    }
+}
+
+/// The effective uid from /proc/self/status, whose `Uid:` line lists real, effective, saved
+/// and filesystem uids.
+fn effective_uid(status: &str) -> Option<u32> {
+    status.lines()
+        .find_map(|line| line.strip_prefix("Uid:"))?
+        .split_whitespace().nth(1)?
+        .parse().ok()
+}
+
+/// Removes `sudo` from every command of a script, for running it as root. Comments are left
+/// alone, as are `sudo` calls with options such as `-u`, which change more than privilege.
+pub fn strip_sudo(script: &str) -> String {
+    let mut stripped = String::new();
+    for line in script.lines() {
+        if line.trim_start().starts_with('#') {
+            stripped.push_str(line);
+        } else {
+            let mut rest = line;
+            while let Some(start) = rest.find("sudo ") {
+                let at_word = rest[..start].chars().last().is_none_or(|c| c.is_whitespace() || matches!(c, '|' | '&' | ';' | '('));
+                let has_options = rest[start + 5..].starts_with('-');
+                stripped.push_str(&rest[..start]);
+                if !at_word || has_options {
+                    stripped.push_str("sudo ");
+                }
+                rest = &rest[start + 5..];
+            }
+            stripped.push_str(rest);
+        }
+        stripped.push('\n');
+    }
+    stripped
+}
+
+/// Removes every use of sudo from a script that runs as root with `TARGET_USER=root`, such as
+/// an image build or cloud-init, where sudo may not be installed. Unlike `strip_sudo`, steps
+/// for the invoking user lose their `sudo -u "$TARGET_USER"` as well, since that user is root.
+pub fn strip_sudo_for_root_user(script: &str) -> String {
+    strip_sudo(&script.replace("sudo -u \"$TARGET_USER\" ", ""))
+}
+
+/// Runs a generated script with sudo, either from `saved_path` or from a temporary file.
+/// Unless `assume_yes` is set, the exact command is shown and must be confirmed first.
+pub fn run(script: &str, saved_path: Option<PathBuf>, two_phase: bool, assume_yes: bool) -> io::Result<()> {
+    if two_phase {
+        if let Some((repos, rest)) = split_phases(script) {
+            return run_two_phase(&repos, &rest, assume_yes);
+        }
+        println!("No repositories selected; running the script in a single phase.");
+    }
+
+    let is_temporary = saved_path.is_none();
+    let script_path = match saved_path {
+        Some(path) => path,
+        None => match save_temp(script) {
+            Some(path) => path,
+            None => return Ok(()),
+        },
+    };
+
+    println!("Exited TUI. Now attempting to run the script with sudo...");
+    println!("--- SCRIPT ---");
+    println!("{}", script);
+    println!("--------------");
+
+    if !confirm_command(&format!("sudo bash {}", script_path.display()), &script_path, assume_yes)? {
+        if is_temporary {
+            remove_temp(&script_path);
+        }
+        return Ok(());
+    }
+    let success = run_file(&script_path)?;
+    if success {
+        println!("\nScript executed successfully.");
+    } else {
+        println!("\nScript execution failed. Please check the output above.");
+    }
+    if !is_temporary {
+        println!("The script was kept at {}", script_path.display());
+    } else {
+        remove_temp(&script_path);
+    }
+    Ok(())
+}
+
+/// Runs the repository phase, shows the resulting repolist, and asks before running the rest.
+fn run_two_phase(repos: &str, rest: &str, assume_yes: bool) -> io::Result<()> {
+    println!("Exited TUI. Phase 1 of 2: enabling repositories...");
+    println!("--- PHASE 1 ---");
+    println!("{}", repos);
+    println!("---------------");
+    let Some(path) = save_temp(repos) else { return Ok(()) };
+    if !confirm_command(&format!("sudo bash {}", path.display()), &path, assume_yes)? {
+        remove_temp(&path);
+        return Ok(());
+    }
+    let success = run_file(&path)?;
+    remove_temp(&path);
+    if !success {
+        println!("\nRepository phase failed. Package installation was not started.");
+        return Ok(());
+    }
+
+    println!("\nRepositories now enabled:");
+    if let Err(err) = Command::new("dnf").arg("repolist").status() {
+        eprintln!("Could not run dnf repolist: {}", err);
+    }
+
+    if assume_yes {
+        println!("\nContinuing with phase 2 (--yes).");
+    } else {
+        print!("\nContinue with phase 2 (package installation and configuration)? [y/N] ");
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().lock().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            println!("Stopped after the repository phase.");
+            return Ok(());
+        }
+    }
+
+    println!("--- PHASE 2 ---");
+    println!("{}", rest);
+    println!("---------------");
+    let Some(path) = save_temp(rest) else { return Ok(()) };
+    // Phase 2 was just confirmed above (or --yes was given), so the command is only shown.
+    println!("Running: sudo bash {}", path.display());
+    let success = run_file(&path)?;
+    remove_temp(&path);
+    if success {
+        println!("\nScript executed successfully.");
+    } else {
+        println!("\nScript execution failed. Please check the output above.");
+    }
+    Ok(())
+}
+
+/// Streams the script to `host` over SSH and runs it there with `sudo bash -s`.
+/// Output is passed through; ssh's own failures (exit code 255) are reported separately.
+pub fn deploy(script: &str, host: &str) -> io::Result<()> {
+    println!("Exited TUI. Running the script on {} over SSH...", host);
+    let mut child = Command::new("ssh").args([host, "sudo bash -s"])
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|err| io::Error::new(err.kind(), format!("could not start ssh: {}", err)))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A write error means ssh exited early; its status below says why.
+        let _ = stdin.write_all(script.as_bytes());
+    }
+    let status = child.wait()?;
+    match status.code() {
+        Some(0) => println!("\nScript executed successfully on {}.", host),
+        Some(255) => println!("\nCould not connect to {} (ssh exit code 255). Check the host and your SSH keys.", host),
+        Some(code) => {
+            println!("\nScript failed on {} with exit code {}.", host, code);
+            println!("If sudo asked for a password, the remote user needs passwordless sudo for --deploy.");
+        }
+        None => println!("\nssh was terminated by a signal."),
+    }
+    Ok(())
+}
+
+/// Installs the script with a oneshot unit that runs it on the next boot, then disables itself
+/// and removes the script once it has succeeded. A failed run is retried on the following boot.
+pub fn install_firstboot(script: &str) -> io::Result<()> {
+    println!("Exited TUI. Installing the script to run on next boot...");
+    let installed = sudo_write(FIRSTBOOT_SCRIPT, script, "0755")?
+        && sudo_write(FIRSTBOOT_UNIT, &firstboot_unit(), "0644")?
+        && Command::new("sudo").args(["systemctl", "daemon-reload"]).status()?.success()
+        && Command::new("sudo").args(["systemctl", "enable", "el-init-firstboot.service"]).status()?.success();
+    if installed {
+        println!("\nInstalled {} and {}.", FIRSTBOOT_SCRIPT, FIRSTBOOT_UNIT);
+        println!("The script will run on the next boot; follow it with: journalctl -u el-init-firstboot");
+    } else {
+        println!("\nInstalling the first-boot unit failed. Please check the output above.");
+    }
+    Ok(())
+}
+
+fn firstboot_unit() -> String {
+    format!("[Unit]
+Description=el-init first-boot provisioning
+Wants=network-online.target
+After=network-online.target
+ConditionPathExists={script}
+
+[Service]
+Type=oneshot
+TimeoutStartSec=0
+ExecStart=/bin/bash {script}
+ExecStartPost=/usr/bin/systemctl disable el-init-firstboot.service
+ExecStartPost=/usr/bin/rm -f {script}
+
+[Install]
+WantedBy=multi-user.target
+", script = FIRSTBOOT_SCRIPT)
+}
+
+/// Checks an `OnCalendar=` expression such as "weekly" or "Sun *-*-* 03:00" and returns its
+/// normalized form. `systemd-analyze calendar` has the final say when it is installed; without
+/// it the expression is only checked for characters that could break the timer unit.
+pub fn validate_schedule(expression: &str) -> Result<String, String> {
+    let expression = expression.trim();
+    if expression.is_empty() {
+        return Err("Enter a schedule, e.g. weekly or Sun *-*-* 03:00.".to_string());
+    }
+    if expression.chars().any(char::is_control) {
+        return Err("The schedule must be a single line.".to_string());
+    }
+    let output = match Command::new("systemd-analyze").args(["calendar", expression]).output() {
+        Ok(output) => output,
+        Err(_) => return Ok(expression.to_string()),
+    };
+    if !output.status.success() {
+        let reason = String::from_utf8_lossy(&output.stderr).lines().next().unwrap_or("").trim().to_string();
+        return Err(format!("Invalid schedule '{}': {}", expression, reason));
+    }
+    let normalized = String::from_utf8_lossy(&output.stdout).lines()
+        .find_map(|line| line.trim().strip_prefix("Normalized form:").map(|form| form.trim().to_string()));
+    Ok(normalized.unwrap_or_else(|| expression.to_string()))
+}
+
+/// Installs the script with a service and a timer that runs it on `schedule`, an `OnCalendar=`
+/// expression checked with `validate_schedule`. Installing again replaces the previous schedule.
+pub fn install_timer(script: &str, schedule: &str) -> io::Result<()> {
+    println!("Exited TUI. Installing the script to run on the schedule '{}'...", schedule);
+    let installed = sudo_write(SCHEDULED_SCRIPT, script, "0755")?
+        && sudo_write(SCHEDULED_SERVICE, &scheduled_service(), "0644")?
+        && sudo_write(SCHEDULED_TIMER, &scheduled_timer(schedule), "0644")?
+        && Command::new("sudo").args(["systemctl", "daemon-reload"]).status()?.success()
+        && Command::new("sudo").args(["systemctl", "enable", "--now", "el-init-scheduled.timer"]).status()?.success();
+    if installed {
+        println!("\nInstalled {}, {} and {}.", SCHEDULED_SCRIPT, SCHEDULED_SERVICE, SCHEDULED_TIMER);
+        println!("See the next run with: systemctl list-timers el-init-scheduled.timer");
+        println!("Follow runs with: journalctl -u el-init-scheduled");
+    } else {
+        println!("\nInstalling the timer failed. Please check the output above.");
+    }
+    Ok(())
+}
+
+fn scheduled_service() -> String {
+    format!("[Unit]
+Description=el-init scheduled maintenance
+Wants=network-online.target
+After=network-online.target
+
+[Service]
+Type=oneshot
+TimeoutStartSec=0
+ExecStart=/bin/bash {script}
+", script = SCHEDULED_SCRIPT)
+}
+
+fn scheduled_timer(schedule: &str) -> String {
+    format!("[Unit]
+Description=Run el-init scheduled maintenance on a schedule
+
+[Timer]
+OnCalendar={schedule}
+Persistent=true
+
+[Install]
+WantedBy=timers.target
+")
+}
+
+/// Writes `content` to a root-owned `path` through `sudo install`.
+fn sudo_write(path: &str, content: &str, mode: &str) -> io::Result<bool> {
+    let mut child = Command::new("sudo").args(["install", "-D", "-m", mode, "/dev/stdin", path])
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(content.as_bytes())?;
+    }
+    Ok(child.wait()?.success())
+}
+
+/// Splits a script at the phase boundary into the repository phase and the remainder.
+/// Both halves keep the shared header (shebang, `set -e`, helpers).
+pub fn split_phases(script: &str) -> Option<(String, String)> {
+    let section_start = script.find(REPOS_SECTION)?;
+    let boundary = script.find(PHASE_BOUNDARY)?;
+    let header = &script[..section_start];
+    let repos = script[..boundary].to_string();
+    let rest = format!("{}{}", header, &script[boundary + PHASE_BOUNDARY.len()..]);
+    Some((repos, rest))
+}
+
+/// Prints the command about to run and the script it runs, then waits for Enter unless
+/// `assume_yes` is set. Returns `false` if the user cancelled.
+fn confirm_command(command: &str, script_path: &Path, assume_yes: bool) -> io::Result<bool> {
+    println!("\nCommand: {}", command);
+    println!("Script:  {}", script_path.display());
+    if assume_yes {
+        return Ok(true);
+    }
+    print!("Press Enter to run it, or type n and Enter to cancel: ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    if matches!(answer.trim(), "n" | "N" | "no") {
+        println!("Cancelled; nothing was run.");
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Invokes the script through bash so it runs even where the directory is mounted noexec.
+fn run_file(path: &Path) -> io::Result<bool> {
+    let status = Command::new("sudo").arg("bash").arg(path).status()?;
+    Ok(status.success())
+}
+
+fn save_temp(script: &str) -> Option<PathBuf> {
+    match write_temp_script(script) {
+        Ok(path) => {
+            println!("Saved temporary script to {}", path.display());
+            Some(path)
+        }
+        Err(err) => {
+            eprintln!("Could not save the temporary script: {}", err);
+            eprintln!("Save the script from the review screen instead and run it manually.");
+            None
+        }
+    }
+}
+
+fn remove_temp(path: &Path) {
+    if let Err(err) = fs::remove_file(path) {
+        eprintln!("Could not remove {}: {}", path.display(), err);
+    }
+}
+
+/// Opens the script in `$VISUAL` or `$EDITOR` (falling back to vi) and returns the edited
+/// text, or `None` if it was left unchanged. The caller must release the terminal first.
+pub fn edit(script: &str) -> Result<Option<String>, String> {
+    let editor = ["VISUAL", "EDITOR"].iter()
+        .find_map(|var| env::var(var).ok().filter(|value| !value.trim().is_empty()))
+        .unwrap_or_else(|| "vi".to_string());
+    let path = write_temp_script(script)?;
+    // Through sh, so editors configured with arguments such as "code --wait" work.
+    let status = Command::new("sh").arg("-c").arg(format!("{} \"$1\"", editor)).arg("sh").arg(&path).status();
+    let edited = fs::read_to_string(&path);
+    remove_temp(&path);
+
+    let status = status.map_err(|err| format!("Failed to start {}: {}", editor, err))?;
+    if !status.success() {
+        return Err(format!("{} exited with {}; the script was not changed", editor, status));
+    }
+    let edited = edited.map_err(|err| format!("Failed to read the edited script: {}", err))?;
+    Ok((edited != script).then_some(edited))
+}
+
+/// Runs `bash -n` on the script and returns the error lines it reports, which are empty
+/// when the syntax is OK. The temporary file's path is stripped, leaving "line N: ..." messages.
+pub fn syntax_check(script: &str) -> Result<Vec<String>, String> {
+    let path = write_temp_script(script)?;
+    let output = Command::new("bash").arg("-n").arg(&path).output();
+    remove_temp(&path);
+    let output = output.map_err(|err| format!("Failed to start bash: {}", err))?;
+    let prefix = format!("{}: ", path.display());
+    Ok(String::from_utf8_lossy(&output.stderr).lines()
+        .map(|line| line.strip_prefix(&prefix).unwrap_or(line).to_string())
+        .collect())
+}
+
+/// Writes the script to the first writable candidate directory and returns its path.
+pub fn write_temp_script(content: &str) -> Result<PathBuf, String> {
+    let mut candidates = Vec::new();
+    if let Some(dir) = env::var_os("TMPDIR").filter(|dir| !dir.is_empty()) {
+        candidates.push(PathBuf::from(dir));
+    }
+    if let Some(home) = env::var_os("HOME").filter(|home| !home.is_empty()) {
+        candidates.push(PathBuf::from(home).join(".cache").join("el-init"));
+    }
+    candidates.push(PathBuf::from("/var/tmp"));
+
+    let mut failures = Vec::new();
+    for dir in candidates {
+        let path = dir.join(format!("el-init-install-{}.sh", std::process::id()));
+        match fs::create_dir_all(&dir).and_then(|_| fs::write(&path, content)) {
+            Ok(_) => return Ok(path),
+            Err(err) => failures.push(format!("{}: {}", dir.display(), err)),
+        }
+    }
+    Err(format!("no writable directory found ({})", failures.join("; ")))
+}
@@ -1,18 +1,25 @@
 // src/scripts.rs
 
-use crate::{MenuNode, OsDistribution, ScriptCategory};
-use std::{cell::RefCell, rc::Rc};
+use crate::{Availability, InstallWeight, ItemMeta, MenuNode, OsDistribution, ScriptCategory};
+use std::{cell::RefCell, fmt, path::Path, rc::Rc};
 
-// The item macro now takes a category.
+// The item macro takes a stable id, a display name, a category, and optional
+// `field = value` metadata overrides. Ids must stay the same across distros and releases.
 macro_rules! item {
-    ($name:expr, $func:expr, $cat:expr) => {
+    ($id:literal, $name:expr, $func:expr, $cat:expr $(, $field:ident = $value:expr)*) => {{
+        #[allow(clippy::needless_update)]
+        let meta = ItemMeta { $($field: $value,)* ..ItemMeta::default() };
         Rc::new(RefCell::new(MenuNode::Item {
+            id: $id,
             name: $name.to_string(),
             script_fn: $func,
             selected: false,
             category: $cat,
+            meta,
+            note: None,
+            availability: Availability::Available,
         }))
-    };
+    }};
 }
 
 // Helper macro to create a branch node (a sub-menu)
@@ -25,74 +32,284 @@ macro_rules! menu {
     };
 }
 
+// Helper macro to create a radio group whose items are mutually exclusive
+macro_rules! radio {
+    ($name:expr, $($child:expr),*) => {
+        Rc::new(RefCell::new(MenuNode::Radio {
+            name: $name.to_string(),
+            children: vec![$($child),*],
+        }))
+    };
+}
+
+/// Runtime values that script functions can interpolate into their commands.
+#[derive(Debug, Clone)]
+pub struct ScriptContext {
+    /// Major version used for the EPEL release RPM.
+    pub epel_version: u32,
+    /// Remote URL registered as the flathub flatpak repository.
+    pub flathub_url: String,
+    /// When set, the script tunes dnf.conf with this `max_parallel_downloads` and fastestmirror.
+    pub parallel_downloads: Option<u32>,
+    /// Whether the KVM installs add the invoking user to the libvirt and kvm groups.
+    pub kvm_group_add: bool,
+    /// Whether steps that modify config files first copy them to `<file>.el-init.bak`.
+    pub backup_configs: bool,
+    /// Whether the script writes each step's name, exit code and duration to `last-run.json`.
+    pub record_run: bool,
+    /// HTTP(S) proxy for dnf, flatpak and downloads, already checked by `validate_proxy`.
+    pub proxy: Option<String>,
+    /// The dnf generation on the target, which decides the config-manager syntax.
+    pub dnf_flavor: DnfFlavor,
+    /// The binary package installs call.
+    pub package_manager: PackageManager,
+}
+
+/// The dnf generation a target ships. EL10 moved to dnf5, whose config-manager is a separate
+/// plugin with its own subcommands.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DnfFlavor {
+    #[default]
+    Dnf4,
+    Dnf5,
+}
+
+impl DnfFlavor {
+    /// The flavor for an EL major version; unknown versions are treated as EL9.
+    pub fn for_version(version: Option<u32>) -> DnfFlavor {
+        match version {
+            Some(major) if major >= 10 => DnfFlavor::Dnf5,
+            _ => DnfFlavor::Dnf4,
+        }
+    }
+
+    /// The package that provides `dnf config-manager`.
+    pub fn plugins_package(self) -> &'static str {
+        match self {
+            DnfFlavor::Dnf4 => "dnf-plugins-core",
+            DnfFlavor::Dnf5 => "dnf5-plugins",
+        }
+    }
+
+    /// The command that enables a repository.
+    pub fn enable_repo(self, repo: &str) -> String {
+        match self {
+            DnfFlavor::Dnf4 => format!("sudo dnf config-manager --set-enabled {}", repo),
+            DnfFlavor::Dnf5 => format!("sudo dnf config-manager setopt {}.enabled=1", repo),
+        }
+    }
+}
+
+/// The package manager a target ships. EL7 only has yum, and minimal images such as UBI
+/// micro ship microdnf without dnf. Every package install and repository enable goes through it.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PackageManager {
+    #[default]
+    Dnf,
+    Yum,
+    Microdnf,
+}
+
+impl PackageManager {
+    /// The manager for this host: yum before EL8, microdnf where it is installed and dnf is not.
+    /// Only meaningful when the script runs here; other targets get the default, dnf.
+    pub fn detect(version: Option<u32>) -> PackageManager {
+        if version.is_some_and(|major| major <= 7) {
+            return PackageManager::Yum;
+        }
+        let installed = |binary: &str| Path::new("/usr/bin").join(binary).exists();
+        if !installed("dnf") && installed("microdnf") { PackageManager::Microdnf } else { PackageManager::Dnf }
+    }
+
+    pub fn binary(self) -> &'static str {
+        match self {
+            PackageManager::Dnf => "dnf",
+            PackageManager::Yum => "yum",
+            PackageManager::Microdnf => "microdnf",
+        }
+    }
+}
+
+impl ScriptContext {
+    /// The command that enables a repository: `yum-config-manager` for yum, `dnf config-manager`
+    /// in the syntax of the target's dnf otherwise.
+    pub fn enable_repo(&self, repo: &str) -> String {
+        match self.package_manager {
+            PackageManager::Yum => format!("sudo yum-config-manager --enable {}", repo),
+            PackageManager::Dnf | PackageManager::Microdnf => self.dnf_flavor.enable_repo(repo),
+        }
+    }
+
+    /// The command that enables a COPR project, given as `owner/project`.
+    pub fn enable_copr(&self, project: &str) -> String {
+        match self.package_manager {
+            PackageManager::Yum => format!("sudo yum copr enable -y {}", project),
+            PackageManager::Dnf | PackageManager::Microdnf => format!("sudo dnf copr enable -y {}", project),
+        }
+    }
+
+    /// The packages that provide `enable_repo` and `enable_copr`. dnf ships both in one
+    /// package; yum has config-manager in yum-utils and copr in a plugin of its own.
+    pub fn plugins_package(&self) -> &'static str {
+        match self.package_manager {
+            PackageManager::Yum => "yum-utils yum-plugin-copr",
+            PackageManager::Dnf | PackageManager::Microdnf => self.dnf_flavor.plugins_package(),
+        }
+    }
+}
+
+impl fmt::Display for PackageManager {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.binary())
+    }
+}
+
+/// The CPU architecture of the target. Virtualization firmware is packaged per architecture.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Arch {
+    #[default]
+    X86_64,
+    Aarch64,
+}
+
+impl Arch {
+    /// The architecture this binary was built for; anything that is not aarch64 is treated as x86_64.
+    pub fn host() -> Arch {
+        match std::env::consts::ARCH {
+            "aarch64" => Arch::Aarch64,
+            _ => Arch::X86_64,
+        }
+    }
+}
+
+/// The EL major version assumed when the target's is not known: the host's could not be read,
+/// or the script is for another distro than the one detected.
+pub const FALLBACK_EL_VERSION: u32 = 9;
+
+impl Default for ScriptContext {
+    fn default() -> Self {
+        ScriptContext {
+            epel_version: FALLBACK_EL_VERSION,
+            flathub_url: "https://dl.flathub.org/repo/flathub.flatpakrepo".to_string(),
+            parallel_downloads: None,
+            kvm_group_add: true,
+            backup_configs: true,
+            record_run: false,
+            proxy: None,
+            dnf_flavor: DnfFlavor::default(),
+            package_manager: PackageManager::default(),
+        }
+    }
+}
+
+/// Checks a proxy URL such as `http://proxy.example.com:3128` and returns it trimmed.
+/// The schemes are the ones dnf's `proxy=` option accepts.
+pub fn validate_proxy(url: &str) -> Result<String, String> {
+    let url = url.trim();
+    let (scheme, rest) = url.split_once("://")
+        .ok_or_else(|| format!("Proxy must be a URL like http://proxy:3128, got '{}'", url))?;
+    if !["http", "https", "socks4", "socks4a", "socks5", "socks5h"].contains(&scheme) {
+        return Err(format!("Unsupported proxy scheme '{}': use http, https or socks5", scheme));
+    }
+    let host = rest.rsplit('@').next().unwrap_or("").trim_end_matches('/');
+    if host.is_empty() || host.starts_with(':') {
+        return Err(format!("Proxy URL '{}' has no host", url));
+    }
+    // The URL is pasted into single-quoted shell words and dnf.conf.
+    if rest.chars().any(|c| c.is_whitespace() || c.is_control() || matches!(c, '\'' | '"' | '\\')) {
+        return Err(format!("Proxy URL '{}' contains spaces or quotes", url));
+    }
+    Ok(url.to_string())
+}
+
+/// A function that renders the commands for a single menu item.
+pub type ScriptFn = fn(&ScriptContext) -> String;
+
 /// Holds all scripts and dynamic names for a specific OS.
 pub struct ScriptSet {
     // KVM
-    kvm_base: fn() -> &'static str,
-    kvm_full: fn() -> &'static str,
-    kvm_virt_manager: fn() -> &'static str,
-    kvm_tigervnc: fn() -> &'static str,
-    kvm_remmina: fn() -> &'static str,
-    kvm_libvirt_net_create: fn() -> &'static str,
+    kvm_base: ScriptFn,
+    kvm_full: ScriptFn,
+    kvm_virt_manager: ScriptFn,
+    kvm_tigervnc: ScriptFn,
+    kvm_remmina: ScriptFn,
+    kvm_libvirt_net_create: ScriptFn,
     // Cockpit
-    cockpit_base: fn() -> &'static str,
-    cockpit_full: fn() -> &'static str,
-    cockpit_storage: fn() -> &'static str,
-    cockpit_podman: fn() -> &'static str,
-    cockpit_files: fn() -> &'static str,
-    cockpit_image_builder: fn() -> &'static str,
-    cockpit_machines: fn() -> &'static str,
+    cockpit_base: ScriptFn,
+    cockpit_full: ScriptFn,
+    cockpit_storage: ScriptFn,
+    cockpit_podman: ScriptFn,
+    cockpit_files: ScriptFn,
+    cockpit_image_builder: ScriptFn,
+    cockpit_machines: ScriptFn,
     // XEN
-    install_xen: fn() -> &'static str,
+    install_xen: ScriptFn,
+    // Containers
+    container_tools: ScriptFn,
+    container_podman: ScriptFn,
+    container_buildah: ScriptFn,
+    container_skopeo: ScriptFn,
+    container_podman_socket: ScriptFn,
+    container_rootless: ScriptFn,
     // Gnome
-    gnome_base: fn() -> &'static str,
-    gnome_full: fn() -> &'static str,
+    gnome_base: ScriptFn,
+    gnome_full: ScriptFn,
     // Gnome Extensions
-    gnome_ext_forge: fn() -> &'static str,
-    gnome_ext_tile: fn() -> &'static str,
-    gnome_ext_paperwm: fn() -> &'static str,
-    gnome_ext_hspacing: fn() -> &'static str,
-    gnome_ext_vitals: fn() -> &'static str,
-    gnome_ext_just_perfection: fn() -> &'static str,
-    gnome_ext_search_light: fn() -> &'static str,
+    gnome_ext_forge: ScriptFn,
+    gnome_ext_tile: ScriptFn,
+    gnome_ext_paperwm: ScriptFn,
+    gnome_ext_hspacing: ScriptFn,
+    gnome_ext_vitals: ScriptFn,
+    gnome_ext_just_perfection: ScriptFn,
+    gnome_ext_search_light: ScriptFn,
     // Gnome Apps
-    app_ptyxis: fn() -> &'static str,
-    app_konsole: fn() -> &'static str,
-    app_alacritty: fn() -> &'static str,
-    app_ghostty: fn() -> &'static str,
-    app_filezilla: fn() -> &'static str,
-    app_remmina: fn() -> &'static str,
-    app_firefox: fn() -> &'static str,
-    app_chromium: fn() -> &'static str,
+    app_ptyxis: ScriptFn,
+    app_konsole: ScriptFn,
+    app_alacritty: ScriptFn,
+    app_ghostty: ScriptFn,
+    term_default_ptyxis: ScriptFn,
+    term_default_konsole: ScriptFn,
+    term_default_alacritty: ScriptFn,
+    term_default_ghostty: ScriptFn,
+    app_filezilla: ScriptFn,
+    app_remmina: ScriptFn,
+    app_firefox: ScriptFn,
+    app_firefox_flatpak: ScriptFn,
+    app_chromium: ScriptFn,
     // Sway
-    sway_compile_1_10: fn() -> &'static str,
-    sway_wofi: fn() -> &'static str,
-    sway_swaybg: fn() -> &'static str,
-    sway_waybar: fn() -> &'static str,
+    sway_compile_1_10: ScriptFn,
+    sway_wofi: ScriptFn,
+    sway_swaybg: ScriptFn,
+    sway_waybar: ScriptFn,
+    sway_xwayland: ScriptFn,
     // Repositories
-    repo_rt: fn() -> &'static str,
-    repo_plus: fn() -> &'static str,
-    repo_nfv: fn() -> &'static str,
-    repo_ha: fn() -> &'static str,
-    repo_extras: fn() -> &'static str,
-    repo_devel: fn() -> &'static str,
-    repo_crb: fn() -> &'static str,
-    repo_baseos: fn() -> &'static str,
-    repo_appstream: fn() -> &'static str,
-    repo_epel: fn() -> &'static str,
-    repo_flathub: fn() -> &'static str,
+    repo_rt: ScriptFn,
+    repo_plus: ScriptFn,
+    repo_nfv: ScriptFn,
+    repo_ha: ScriptFn,
+    repo_extras: ScriptFn,
+    repo_devel: ScriptFn,
+    repo_crb: ScriptFn,
+    repo_baseos: ScriptFn,
+    repo_appstream: ScriptFn,
+    repo_epel: ScriptFn,
+    repo_flathub: ScriptFn,
+    // COPR Repositories
+    copr_ptyxis: ScriptFn,
+    copr_alacritty: ScriptFn,
+    copr_ghostty: ScriptFn,
     // FIX: Add Networking fields
-    net_vpn_ovpn: fn() -> &'static str,
-    net_vpn_l2tp: fn() -> &'static str,
-    net_vpn_sswan: fn() -> &'static str,
-    net_vpn_lswan: fn() -> &'static str,
-    net_vpn_pptp: fn() -> &'static str,
-    net_vpn_oconn: fn() -> &'static str,
+    net_vpn_ovpn: ScriptFn,
+    net_vpn_l2tp: ScriptFn,
+    net_vpn_sswan: ScriptFn,
+    net_vpn_lswan: ScriptFn,
+    net_vpn_pptp: ScriptFn,
+    net_vpn_oconn: ScriptFn,
 }
 
-/// This function is the single source of truth for OS-specific scripts.
-pub fn get_script_set(_os: OsDistribution) -> ScriptSet {
-    ScriptSet {
+/// This function is the single source of truth for OS- and architecture-specific scripts.
+pub fn get_script_set(os: OsDistribution, arch: Arch) -> ScriptSet {
+    let mut set = ScriptSet {
         // KVM
         kvm_base: scripts_virt::kvm_base,
         kvm_full: scripts_virt::kvm_full,
@@ -110,6 +327,13 @@ pub fn get_script_set(_os: OsDistribution) -> ScriptSet {
         cockpit_machines: scripts_virt::cockpit_machines,
         // XEN
         install_xen: scripts_virt::install_xen,
+        // Containers
+        container_tools: scripts_containers::container_tools,
+        container_podman: scripts_containers::podman,
+        container_buildah: scripts_containers::buildah,
+        container_skopeo: scripts_containers::skopeo,
+        container_podman_socket: scripts_containers::podman_socket,
+        container_rootless: scripts_containers::rootless_setup,
         // Gnome
         gnome_base: scripts_gnome::base_install,
         gnome_full: scripts_gnome::full_install,
@@ -122,19 +346,25 @@ pub fn get_script_set(_os: OsDistribution) -> ScriptSet {
         gnome_ext_just_perfection: scripts_gnome_ext::placeholder,
         gnome_ext_search_light: scripts_gnome_ext::placeholder,
         // Gnome Apps
-        app_ptyxis: scripts_gnome_apps::placeholder,
+        app_ptyxis: scripts_gnome_apps::ptyxis,
         app_konsole: scripts_gnome_apps::konsole,
-        app_alacritty: scripts_gnome_apps::placeholder,
-        app_ghostty: scripts_gnome_apps::placeholder,
+        app_alacritty: scripts_gnome_apps::alacritty,
+        app_ghostty: scripts_gnome_apps::ghostty,
+        term_default_ptyxis: scripts_gnome_apps::default_ptyxis,
+        term_default_konsole: scripts_gnome_apps::default_konsole,
+        term_default_alacritty: scripts_gnome_apps::default_alacritty,
+        term_default_ghostty: scripts_gnome_apps::default_ghostty,
         app_filezilla: scripts_gnome_apps::filezilla,
         app_remmina: scripts_gnome_apps::remmina,
         app_firefox: scripts_gnome_apps::firefox,
+        app_firefox_flatpak: scripts_flatpak::firefox,
         app_chromium: scripts_gnome_apps::chromium,
         // Sway
         sway_compile_1_10: scripts_sway::compile_from_source,
         sway_wofi: scripts_sway::install_wofi,
         sway_swaybg: scripts_sway::install_swaybg,
         sway_waybar: scripts_sway::install_waybar,
+        sway_xwayland: scripts_sway::install_xwayland,
         // Repositories (Rocky Specific)
         repo_rt: scripts_repos::add_rt,
         repo_plus: scripts_repos::add_plus,
@@ -147,6 +377,10 @@ pub fn get_script_set(_os: OsDistribution) -> ScriptSet {
         repo_appstream: scripts_repos::add_appstream,
         repo_epel: scripts_repos::add_epel,
         repo_flathub: scripts_repos::add_flathub,
+        // COPR Repositories
+        copr_ptyxis: scripts_copr::enable_ptyxis,
+        copr_alacritty: scripts_copr::enable_alacritty,
+        copr_ghostty: scripts_copr::enable_ghostty,
         // FIX: Populate Networking fields
         net_vpn_ovpn: scripts_net::install_vpn_ovpn,
         net_vpn_l2tp: scripts_net::install_vpn_l2tp,
@@ -154,220 +388,488 @@ pub fn get_script_set(_os: OsDistribution) -> ScriptSet {
         net_vpn_lswan: scripts_net::install_vpn_lswan,
         net_vpn_pptp: scripts_net::install_vpn_pptp,
         net_vpn_oconn: scripts_net::install_vpn_oconn,
+    };
+
+    // CentOS Stream uses different ids for some repos.
+    if os == OsDistribution::CentosStream {
+        set.repo_ha = scripts_repos::add_ha_stream;
+        set.repo_extras = scripts_repos::add_extras_stream;
     }
+    // RHEL repos are managed by subscription-manager; config-manager would fight it.
+    if os == OsDistribution::Rhel {
+        set.repo_rt = scripts_rhel_repos::add_rt;
+        set.repo_nfv = scripts_rhel_repos::add_nfv;
+        set.repo_ha = scripts_rhel_repos::add_ha;
+        set.repo_crb = scripts_rhel_repos::add_crb;
+        set.repo_baseos = scripts_rhel_repos::add_baseos;
+        set.repo_appstream = scripts_rhel_repos::add_appstream;
+        set.repo_epel = scripts_rhel_repos::add_epel;
+    }
+    // aarch64 boots guests with AAVMF from edk2-aarch64 instead of OVMF.
+    if arch == Arch::Aarch64 {
+        set.kvm_base = scripts_virt::kvm_base_aarch64;
+    }
+    set
 }
 
-/// Recursively sorts the children of menu nodes alphabetically.
-fn sort_menu_recursively(node: &Rc<RefCell<MenuNode>>) {
-    if let Ok(mut node_borrow) = node.try_borrow_mut() {
-        if let MenuNode::Menu { children, .. } = &mut *node_borrow {
-            children.sort_by(|a, b| {
-                let a_name = match &*a.borrow() {
-                    MenuNode::Menu { name, .. } => name.clone(),
-                    MenuNode::Item { name, .. } => name.clone(),
-                };
-                let b_name = match &*b.borrow() {
-                    MenuNode::Menu { name, .. } => name.clone(),
-                    MenuNode::Item { name, .. } => name.clone(),
-                };
-                a_name.to_lowercase().cmp(&b_name.to_lowercase())
-            });
+/// Why an item cannot be used on `os`, if it cannot.
+fn unavailable_reason(os: OsDistribution, id: &str) -> Option<&'static str> {
+    match (os, id) {
+        (OsDistribution::CentosStream, "repo-plus" | "repo-devel") => Some("not available on CentOS Stream"),
+        (OsDistribution::Rhel, "repo-plus" | "repo-devel" | "repo-extras") => Some("not available on RHEL"),
+        _ => None,
+    }
+}
+
+/// Why `package_manager` cannot run an item's commands, if it cannot. microdnf has neither
+/// config-manager, the copr plugin nor group installs.
+fn unsupported_reason(package_manager: PackageManager, script: &str) -> Option<&'static str> {
+    if package_manager != PackageManager::Microdnf {
+        None
+    } else if script.contains("config-manager") {
+        Some("needs dnf: microdnf cannot enable repositories")
+    } else if script.contains("copr enable") {
+        Some("needs dnf: microdnf cannot enable COPRs")
+    } else if script.contains("groupinstall") || script.contains("group install") {
+        Some("needs dnf: microdnf has no group installs")
+    } else {
+        None
+    }
+}
 
+/// Marks the items whose commands `ctx.package_manager` cannot run as unavailable and
+/// clears their selection.
+pub fn mark_unsupported(node: &Rc<RefCell<MenuNode>>, ctx: &ScriptContext) {
+    match &mut *node.borrow_mut() {
+        MenuNode::Item { script_fn, selected, availability, .. } => {
+            if *availability == Availability::Available
+                && let Some(reason) = unsupported_reason(ctx.package_manager, &script_fn(ctx))
+            {
+                *availability = Availability::Unavailable(reason);
+                *selected = false;
+            }
+        }
+        MenuNode::Menu { children, .. } | MenuNode::Radio { children, .. } => {
+            for child in children {
+                mark_unsupported(child, ctx);
+            }
+        }
+    }
+}
+
+/// What a repository item actually enables, for the `d` detail popup.
+pub fn repo_details(id: &str) -> Option<&'static str> {
+    Some(match id {
+        "repo-rt" => "Real Time (rt) carries kernel-rt, a kernel built with the PREEMPT_RT patches, along with \
+            tuned-profiles-realtime and rteval for validating latency. It is meant for latency-sensitive work such as \
+            industrial control, audio or trading; general-purpose servers and desktops do not need it. The real-time \
+            kernel only takes effect after booting into it.",
+        "repo-plus" => "Plus is a Rocky Linux repository with packages that Rocky builds in addition to what RHEL ships, \
+            mostly variants of existing packages with features enabled that RHEL leaves out. It is disabled by default \
+            and does not exist on CentOS Stream or RHEL.",
+        "repo-nfv" => "Network Functions Virtualization (nfv) targets telco hosts that run virtual network functions. \
+            It provides the real-time kernel variant for NFV together with tuned-profiles-nfv and related tuning packages \
+            for low-latency packet processing. Only useful on dedicated NFV compute nodes.",
+        "repo-ha" => "High Availability provides the Pacemaker cluster stack: pacemaker, corosync, pcs, fence-agents \
+            and resource-agents. Enable it to build active/passive or active/active failover clusters.",
+        "repo-extras" => "Extras contains a small set of packages that complement the base OS, most notably the release \
+            packages for the Special Interest Group repositories (for example the kernel modules, virtualization and \
+            storage SIGs). Installing one of those release packages is what enables the SIG repository itself.",
+        "repo-devel" => "Devel exposes build dependencies and -devel packages that the distribution uses to build itself \
+            but does not support. It exists for building packages, not for running systems: its packages can replace \
+            supported ones and are not kept in sync for production use. Enable it temporarily, if at all.",
+        "repo-crb" => "CodeReady Builder (CRB, formerly PowerTools) holds development headers, libraries and build tools \
+            that are not in BaseOS or AppStream, such as many -devel packages and documentation generators. A large \
+            part of EPEL depends on it, which is why selecting EPEL adds CRB as well.",
+        "repo-baseos" => "BaseOS provides the core operating system: the kernel, systemd, dnf, glibc and the other \
+            packages every installation needs. It is enabled by default; enabling it again only repairs a system where \
+            it was switched off.",
+        "repo-appstream" => "AppStream provides user-space applications, language runtimes, databases and desktop \
+            software, including module streams that offer several versions of the same software (for example nodejs or \
+            postgresql). It is enabled by default.",
+        "repo-epel" => "Extra Packages for Enterprise Linux (EPEL) is maintained by the Fedora project and rebuilds \
+            thousands of community packages for EL, such as htop, the Sway desktop and many Python and Perl modules. \
+            EPEL never replaces base packages, but it is not supported by the distribution vendor. It needs CRB.",
+        "repo-flathub" => "Flathub is the main Flatpak remote. It installs flatpak and registers flathub, which then \
+            provides sandboxed desktop applications such as Firefox that update independently of the OS packages.",
+        "copr-ptyxis" => "A community build of the Ptyxis terminal from Fedora COPR (chergert/ptyxis). COPR projects are \
+            built by individual maintainers and are not reviewed by the distribution; trust depends on the maintainer.",
+        "copr-alacritty" => "A community build of the Alacritty GPU-accelerated terminal from Fedora COPR (atim/alacritty). \
+            COPR projects are built by individual maintainers and are not reviewed by the distribution.",
+        "copr-ghostty" => "A community build of the Ghostty terminal from Fedora COPR (scottames/ghostty). COPR projects \
+            are built by individual maintainers and are not reviewed by the distribution.",
+        _ => return None,
+    })
+}
+
+/// Marks the items that cannot be used on `os` as unavailable.
+fn mark_unavailable(node: &Rc<RefCell<MenuNode>>, os: OsDistribution) {
+    match &mut *node.borrow_mut() {
+        MenuNode::Item { id, availability, .. } => {
+            if let Some(reason) = unavailable_reason(os, id) {
+                *availability = Availability::Unavailable(reason);
+            }
+        }
+        MenuNode::Menu { children, .. } | MenuNode::Radio { children, .. } => {
             for child in children {
-                sort_menu_recursively(child);
+                mark_unavailable(child, os);
             }
         }
     }
 }
 
+/// Recursively sorts the children of menu nodes alphabetically.
+fn sort_menu_recursively(node: &Rc<RefCell<MenuNode>>) {
+    if let Ok(mut node_borrow) = node.try_borrow_mut()
+        && let MenuNode::Menu { children, .. } | MenuNode::Radio { children, .. } = &mut *node_borrow
+    {
+        children.sort_by_key(|child| child.borrow().name().to_lowercase());
+
+        for child in children {
+            sort_menu_recursively(child);
+        }
+    }
+}
+
 /// Builds the menu tree using a generic ScriptSet.
-pub fn build_menu_tree(os: OsDistribution) -> Rc<RefCell<MenuNode>> {
-    let scripts = get_script_set(os);
+pub fn build_menu_tree(os: OsDistribution, arch: Arch, preselect_defaults: bool) -> Rc<RefCell<MenuNode>> {
+    let scripts = get_script_set(os, arch);
 
     let main_menu = menu!("Main Menu",
         menu!("Virtualization",
             menu!("Virtualization Engines",
                 menu!("KVM Core & Tools",
-                    item!("Base Installation", scripts.kvm_base, ScriptCategory::General),
-                    item!("Full Installation", scripts.kvm_full, ScriptCategory::General),
+                    item!("kvm-base", "Base Installation", scripts.kvm_base, ScriptCategory::General, weight = Some(InstallWeight::Medium), tags = &["server"]),
+                    item!("kvm-full", "Full Installation", scripts.kvm_full, ScriptCategory::General, weight = Some(InstallWeight::Heavy), tags = &["server"]),
                     menu!("Modules",
-                        item!("virt-manager", scripts.kvm_virt_manager, ScriptCategory::General),
-                        item!("tigervnc", scripts.kvm_tigervnc, ScriptCategory::General),
-                        item!("remmina", scripts.kvm_remmina, ScriptCategory::General)
+                        item!("kvm-virt-manager", "virt-manager", scripts.kvm_virt_manager, ScriptCategory::General, requires = &["kvm-base"], tags = &["gui"]),
+                        item!("kvm-tigervnc", "tigervnc", scripts.kvm_tigervnc, ScriptCategory::General, tags = &["server", "network"]),
+                        item!("kvm-remmina", "remmina", scripts.kvm_remmina, ScriptCategory::General, tags = &["gui", "network"])
                     ),
                     menu!("Setup Scripts",
-                        item!("libvirt network create", scripts.kvm_libvirt_net_create, ScriptCategory::General)
+                        item!("kvm-libvirt-net-create", "libvirt network create", scripts.kvm_libvirt_net_create, ScriptCategory::General, requires = &["kvm-base"], is_stub = true, tags = &["network", "experimental"])
                     )
                 ),
                 menu!("XEN Core & Tools",
-                    item!("Base Installation", scripts.install_xen, ScriptCategory::General)
+                    item!("install-xen", "Base Installation", scripts.install_xen, ScriptCategory::General, weight = Some(InstallWeight::Medium), needs_reboot = true, tags = &["server", "experimental"])
                 ),
                 menu!("XEN Management",)
             ),
             menu!("KVM Management",
                 menu!("Cockpit",
-                    item!("Base Installation", scripts.cockpit_base, ScriptCategory::General),
-                    item!("Full Installation", scripts.cockpit_full, ScriptCategory::General),
+                    item!("cockpit-base", "Base Installation", scripts.cockpit_base, ScriptCategory::General, weight = Some(InstallWeight::Light), tags = &["server", "network"]),
+                    item!("cockpit-full", "Full Installation", scripts.cockpit_full, ScriptCategory::General, weight = Some(InstallWeight::Medium), tags = &["server", "network"]),
                     menu!("Modules",
-                        item!("storage", scripts.cockpit_storage, ScriptCategory::General),
-                        item!("podman", scripts.cockpit_podman, ScriptCategory::General),
-                        item!("files", scripts.cockpit_files, ScriptCategory::General),
-                        item!("image builder", scripts.cockpit_image_builder, ScriptCategory::General),
-                        item!("machines", scripts.cockpit_machines, ScriptCategory::General)
+                        item!("cockpit-storage", "storage", scripts.cockpit_storage, ScriptCategory::General, requires = &["cockpit-base"], tags = &["server"]),
+                        item!("cockpit-podman", "podman", scripts.cockpit_podman, ScriptCategory::General, requires = &["cockpit-base"], tags = &["server"]),
+                        item!("cockpit-files", "files", scripts.cockpit_files, ScriptCategory::General, requires = &["cockpit-base"], tags = &["server"]),
+                        item!("cockpit-image-builder", "image builder", scripts.cockpit_image_builder, ScriptCategory::General, requires = &["cockpit-base"], tags = &["server"]),
+                        item!("cockpit-machines", "machines", scripts.cockpit_machines, ScriptCategory::General, requires = &["cockpit-base", "kvm-base"], tags = &["server"])
                     )
                 )
             )
         ),
+        menu!("Containers",
+            item!("container-tools", "container-tools (all of the below)", scripts.container_tools, ScriptCategory::General, weight = Some(InstallWeight::Medium), tags = &["server"]),
+            menu!("Tools",
+                item!("container-podman", "podman", scripts.container_podman, ScriptCategory::General, weight = Some(InstallWeight::Light), tags = &["server"]),
+                item!("container-buildah", "buildah", scripts.container_buildah, ScriptCategory::General, weight = Some(InstallWeight::Light), tags = &["server"]),
+                item!("container-skopeo", "skopeo", scripts.container_skopeo, ScriptCategory::General, weight = Some(InstallWeight::Light), tags = &["server"])
+            ),
+            menu!("Setup Scripts",
+                item!("container-podman-socket", "Enable podman socket", scripts.container_podman_socket, ScriptCategory::General, tags = &["server"]),
+                item!("container-rootless", "Rootless setup for invoking user", scripts.container_rootless, ScriptCategory::General, tags = &["server"], touches_config = &["/etc/subuid", "/etc/subgid"])
+            )
+        ),
         menu!("Graphical Environments",
             menu!("Gnome DE - STABLE",
                 menu!("Environment Installation",
-                    item!("Base Installation", scripts.gnome_base, ScriptCategory::General),
-                    item!("Full Installation", scripts.gnome_full, ScriptCategory::General)
+                    item!("gnome-base", "Base Installation", scripts.gnome_base, ScriptCategory::General, weight = Some(InstallWeight::Medium), needs_reboot = true, tags = &["gui"]),
+                    item!("gnome-full", "Full Installation", scripts.gnome_full, ScriptCategory::General, weight = Some(InstallWeight::Heavy), needs_reboot = true, tags = &["gui"])
                 ),
                 menu!("Customization / Extensions",
                     menu!("Tiling WM",
-                        item!("Forge", scripts.gnome_ext_forge, ScriptCategory::General),
-                        item!("Tile", scripts.gnome_ext_tile, ScriptCategory::General),
-                        item!("PaperWM", scripts.gnome_ext_paperwm, ScriptCategory::General)
+                        item!("gnome-ext-forge", "Forge", scripts.gnome_ext_forge, ScriptCategory::General, is_stub = true, tags = &["gui", "experimental"]),
+                        item!("gnome-ext-tile", "Tile", scripts.gnome_ext_tile, ScriptCategory::General, is_stub = true, tags = &["gui", "experimental"]),
+                        item!("gnome-ext-paperwm", "PaperWM", scripts.gnome_ext_paperwm, ScriptCategory::General, is_stub = true, tags = &["gui", "experimental"])
                     ),
                     menu!("Top Bar",
-                        item!("status area horizontal spacing", scripts.gnome_ext_hspacing, ScriptCategory::General),
-                        item!("vitals", scripts.gnome_ext_vitals, ScriptCategory::General)
+                        item!("gnome-ext-hspacing", "status area horizontal spacing", scripts.gnome_ext_hspacing, ScriptCategory::General, is_stub = true, tags = &["gui", "experimental"]),
+                        item!("gnome-ext-vitals", "vitals", scripts.gnome_ext_vitals, ScriptCategory::General, is_stub = true, tags = &["gui", "experimental"])
                     ),
                     menu!("Tweaks",
-                        item!("Just Perfection", scripts.gnome_ext_just_perfection, ScriptCategory::General)
+                        item!("gnome-ext-just-perfection", "Just Perfection", scripts.gnome_ext_just_perfection, ScriptCategory::General, is_stub = true, tags = &["gui", "experimental"])
                     ),
                     menu!("Search / Launchers",
-                        item!("Search Light", scripts.gnome_ext_search_light, ScriptCategory::General)
+                        item!("gnome-ext-search-light", "Search Light", scripts.gnome_ext_search_light, ScriptCategory::General, is_stub = true, tags = &["gui", "experimental"])
                     )
                 ),
                 menu!("Applications / Packages",
                     menu!("Terminals",
-                        item!("Ptyxis", scripts.app_ptyxis, ScriptCategory::General),
-                        item!("Konsole", scripts.app_konsole, ScriptCategory::General),
-                        item!("Allacritty", scripts.app_alacritty, ScriptCategory::General),
-                        item!("Ghostty", scripts.app_ghostty, ScriptCategory::General)
+                        item!("app-ptyxis", "Ptyxis", scripts.app_ptyxis, ScriptCategory::General, tags = &["gui"]),
+                        item!("app-konsole", "Konsole", scripts.app_konsole, ScriptCategory::General, tags = &["gui"]),
+                        item!("app-alacritty", "Allacritty", scripts.app_alacritty, ScriptCategory::General, tags = &["gui"]),
+                        item!("app-ghostty", "Ghostty", scripts.app_ghostty, ScriptCategory::General, tags = &["gui"]),
+                        radio!("Default Terminal",
+                            item!("term-default-ptyxis", "Ptyxis", scripts.term_default_ptyxis, ScriptCategory::General, requires = &["app-ptyxis"], tags = &["gui"]),
+                            item!("term-default-konsole", "Konsole", scripts.term_default_konsole, ScriptCategory::General, requires = &["app-konsole"], tags = &["gui"]),
+                            item!("term-default-alacritty", "Allacritty", scripts.term_default_alacritty, ScriptCategory::General, requires = &["app-alacritty"], tags = &["gui"]),
+                            item!("term-default-ghostty", "Ghostty", scripts.term_default_ghostty, ScriptCategory::General, requires = &["app-ghostty"], tags = &["gui"])
+                        )
                     ),
                     menu!("Remote Connection",
-                        item!("Filezilla", scripts.app_filezilla, ScriptCategory::General),
-                        item!("Remmina", scripts.app_remmina, ScriptCategory::General)
+                        item!("app-filezilla", "Filezilla", scripts.app_filezilla, ScriptCategory::General, tags = &["gui", "network"]),
+                        item!("app-remmina", "Remmina", scripts.app_remmina, ScriptCategory::General, tags = &["gui", "network"])
                     ),
                     menu!("Browsers",
-                        item!("Firefox", scripts.app_firefox, ScriptCategory::General),
-                        item!("Chromium", scripts.app_chromium, ScriptCategory::General)
+                        item!("app-firefox", "Firefox", scripts.app_firefox, ScriptCategory::General, tags = &["gui", "network"]),
+                        item!("app-firefox-flatpak", "Firefox (Flatpak)", scripts.app_firefox_flatpak, ScriptCategory::General, requires = &["repo-flathub"], tags = &["gui", "network"]),
+                        item!("app-chromium", "Chromium", scripts.app_chromium, ScriptCategory::General, tags = &["gui", "network"])
                     )
                 )
             ),
             menu!("Sway WM",
                 menu!("Environment Installation",
                     menu!("Compile from Source",
-                        item!("v1.10", scripts.sway_compile_1_10, ScriptCategory::General)
-                    )
+                        item!("sway-compile-1-10", "v1.10", scripts.sway_compile_1_10, ScriptCategory::General, weight = Some(InstallWeight::Heavy),
+                            wayland_only = true, requires = &["sway-xwayland"], is_stub = true, tags = &["gui", "experimental"])
+                    ),
+                    item!("sway-xwayland", "Xwayland (X11 app support)", scripts.sway_xwayland, ScriptCategory::General, tags = &["gui"])
                 ),
                 menu!("Customization / Extentsions",
-                    item!("Wofi", scripts.sway_wofi, ScriptCategory::General),
-                    item!("Swaybg", scripts.sway_swaybg, ScriptCategory::General),
-                    item!("Waybar", scripts.sway_waybar, ScriptCategory::General)
+                    item!("sway-wofi", "Wofi", scripts.sway_wofi, ScriptCategory::General, wayland_only = true, tags = &["gui"]),
+                    item!("sway-swaybg", "Swaybg", scripts.sway_swaybg, ScriptCategory::General, wayland_only = true, tags = &["gui"]),
+                    item!("sway-waybar", "Waybar", scripts.sway_waybar, ScriptCategory::General, wayland_only = true, tags = &["gui"])
                 )
             )
         ),
         // FIX: Add Networking menu back
         menu!("Networking",
             menu!("NetworkManager",
-                item!("OpenVPN", scripts.net_vpn_ovpn, ScriptCategory::General),
-                item!("OpenConnect", scripts.net_vpn_oconn, ScriptCategory::General),
-                item!("L2TP", scripts.net_vpn_l2tp, ScriptCategory::General),
-                item!("LibreSwan", scripts.net_vpn_lswan, ScriptCategory::General),
-                item!("StrongSwan", scripts.net_vpn_sswan, ScriptCategory::General),
-                item!("PPTP", scripts.net_vpn_pptp, ScriptCategory::General)
+                item!("net-vpn-ovpn", "OpenVPN", scripts.net_vpn_ovpn, ScriptCategory::General, tags = &["network"]),
+                item!("net-vpn-oconn", "OpenConnect", scripts.net_vpn_oconn, ScriptCategory::General, tags = &["network"]),
+                item!("net-vpn-l2tp", "L2TP", scripts.net_vpn_l2tp, ScriptCategory::General, tags = &["network"]),
+                item!("net-vpn-lswan", "LibreSwan", scripts.net_vpn_lswan, ScriptCategory::General, tags = &["network"]),
+                item!("net-vpn-sswan", "StrongSwan", scripts.net_vpn_sswan, ScriptCategory::General, tags = &["network"]),
+                item!("net-vpn-pptp", "PPTP", scripts.net_vpn_pptp, ScriptCategory::General, tags = &["network"])
             )
         ),
         menu!("Repositories",
             menu!("Add Repositories (ROCKY LINUX SPECIFIC)",
-                item!("realtime", scripts.repo_rt, ScriptCategory::Repository),
-                item!("plus", scripts.repo_plus, ScriptCategory::Repository),
-                item!("nfv", scripts.repo_nfv, ScriptCategory::Repository),
-                item!("High availibility", scripts.repo_ha, ScriptCategory::Repository),
-                item!("extras", scripts.repo_extras, ScriptCategory::Repository),
-                item!("devel (WARNING)", scripts.repo_devel, ScriptCategory::Repository),
-                item!("CRB (code ready builder)", scripts.repo_crb, ScriptCategory::Repository),
-                item!("base OS", scripts.repo_baseos, ScriptCategory::Repository),
-                item!("appstream", scripts.repo_appstream, ScriptCategory::Repository),
-                item!("epel", scripts.repo_epel, ScriptCategory::Repository),
-                item!("flathub", scripts.repo_flathub, ScriptCategory::Repository)
+                item!("repo-rt", "realtime", scripts.repo_rt, ScriptCategory::Repository, tags = &["server"], touches_config = &["/etc/yum.repos.d/*.repo"]),
+                item!("repo-plus", "plus", scripts.repo_plus, ScriptCategory::Repository, touches_config = &["/etc/yum.repos.d/*.repo"]),
+                item!("repo-nfv", "nfv", scripts.repo_nfv, ScriptCategory::Repository, tags = &["server", "network"], touches_config = &["/etc/yum.repos.d/*.repo"]),
+                item!("repo-ha", "High availibility", scripts.repo_ha, ScriptCategory::Repository, tags = &["server"], touches_config = &["/etc/yum.repos.d/*.repo"]),
+                item!("repo-extras", "extras", scripts.repo_extras, ScriptCategory::Repository, touches_config = &["/etc/yum.repos.d/*.repo"]),
+                item!("repo-devel", "devel (WARNING)", scripts.repo_devel, ScriptCategory::Repository, tags = &["experimental"], touches_config = &["/etc/yum.repos.d/*.repo"]),
+                item!("repo-crb", "CRB (code ready builder)", scripts.repo_crb, ScriptCategory::Repository, touches_config = &["/etc/yum.repos.d/*.repo"]),
+                item!("repo-baseos", "base OS", scripts.repo_baseos, ScriptCategory::Repository, touches_config = &["/etc/yum.repos.d/*.repo"]),
+                item!("repo-appstream", "appstream", scripts.repo_appstream, ScriptCategory::Repository, touches_config = &["/etc/yum.repos.d/*.repo"]),
+                item!("repo-epel", "epel", scripts.repo_epel, ScriptCategory::Repository, requires = &["repo-crb"], touches_config = &["/etc/yum.repos.d/*.repo"]),
+                item!("repo-flathub", "flathub", scripts.repo_flathub, ScriptCategory::Repository, touches_config = &["/var/lib/flatpak/repo/config"])
+            ),
+            menu!("COPR Repositories",
+                item!("copr-ptyxis", "chergert/ptyxis", scripts.copr_ptyxis, ScriptCategory::Copr, tags = &["gui", "experimental"]),
+                item!("copr-alacritty", "atim/alacritty", scripts.copr_alacritty, ScriptCategory::Copr, tags = &["gui", "experimental"]),
+                item!("copr-ghostty", "scottames/ghostty", scripts.copr_ghostty, ScriptCategory::Copr, tags = &["gui", "experimental"])
             )
         )
     );
 
     sort_menu_recursively(&main_menu);
+    mark_unavailable(&main_menu, os);
+    if preselect_defaults {
+        main_menu.borrow_mut().select_ids(&default_selection(os).iter().copied().collect());
+    }
     main_menu
 }
 
+/// Repositories most installs of `os` end up enabling: baseos and appstream are on already,
+/// while CRB and EPEL are needed by much of the rest of the menu.
+fn default_selection(os: OsDistribution) -> &'static [&'static str] {
+    match os {
+        OsDistribution::Unknown => &[],
+        _ => &["repo-crb", "repo-epel"],
+    }
+}
+
 // --- Script Content Modules ---
 
 mod scripts_virt {
-    pub fn kvm_base() -> &'static str { "sudo dnf install -y qemu-kvm libvirt-daemon-config-network libvirt-daemon-kvm" }
-    pub fn kvm_full() -> &'static str { "sudo dnf install -y @virtualization virt-top libguestfs-tools" }
-    pub fn kvm_virt_manager() -> &'static str { "sudo dnf install -y virt-manager" }
-    pub fn kvm_tigervnc() -> &'static str { "sudo dnf install -y tigervnc-server" }
-    pub fn kvm_remmina() -> &'static str { "sudo dnf install -y remmina" }
-    pub fn kvm_libvirt_net_create() -> &'static str { "echo 'Placeholder for libvirt network creation script'" }
+    use super::ScriptContext;
+
+    pub fn kvm_base(ctx: &ScriptContext) -> String {
+        with_group_add(ctx, &format!("sudo {} install -y qemu-kvm libvirt-daemon-config-network libvirt-daemon-kvm edk2-ovmf", ctx.package_manager))
+    }
+    pub fn kvm_base_aarch64(ctx: &ScriptContext) -> String {
+        with_group_add(ctx, &format!("sudo {} install -y qemu-kvm libvirt-daemon-config-network libvirt-daemon-kvm edk2-aarch64", ctx.package_manager))
+    }
+    pub fn kvm_full(ctx: &ScriptContext) -> String {
+        with_group_add(ctx, &format!("sudo {} install -y @virtualization virt-top libguestfs-tools", ctx.package_manager))
+    }
+
+    /// Appends the libvirt/kvm group membership step when it is enabled. Membership that is
+    /// already in place is left alone, so selecting both installs adds the groups once.
+    fn with_group_add(ctx: &ScriptContext, install: &str) -> String {
+        if !ctx.kvm_group_add {
+            return install.to_string();
+        }
+        format!(r#"{install}
+for group in libvirt kvm; do
+    if [ "$TARGET_USER" != root ] && ! id -nG "$TARGET_USER" | grep -qw "$group"; then
+        sudo usermod -aG "$group" "$TARGET_USER"
+        echo "Added $TARGET_USER to the $group group; log out and back in to manage VMs without sudo."
+    fi
+done"#)
+    }
+    pub fn kvm_virt_manager(ctx: &ScriptContext) -> String { format!("sudo {} install -y virt-manager", ctx.package_manager) }
+    pub fn kvm_tigervnc(ctx: &ScriptContext) -> String { format!("sudo {} install -y tigervnc-server", ctx.package_manager) }
+    pub fn kvm_remmina(ctx: &ScriptContext) -> String { format!("sudo {} install -y remmina", ctx.package_manager) }
+    pub fn kvm_libvirt_net_create(_ctx: &ScriptContext) -> String { "echo 'Placeholder for libvirt network creation script'".into() }
     
-    pub fn cockpit_base() -> &'static str { "sudo dnf install -y cockpit\nsudo systemctl enable --now cockpit.socket" }
-    pub fn cockpit_full() -> &'static str { "sudo dnf install -y cockpit cockpit-machines cockpit-podman cockpit-storaged\nsudo systemctl enable --now cockpit.socket" }
-    pub fn cockpit_storage() -> &'static str { "sudo dnf install -y cockpit-storaged" }
-    pub fn cockpit_podman() -> &'static str { "sudo dnf install -y cockpit-podman" }
-    pub fn cockpit_files() -> &'static str { "echo 'cockpit-files is part of the core cockpit package'" }
-    pub fn cockpit_image_builder() -> &'static str { "sudo dnf install -y cockpit-composer" }
-    pub fn cockpit_machines() -> &'static str { "sudo dnf install -y cockpit-machines" }
+    pub fn cockpit_base(ctx: &ScriptContext) -> String { format!("sudo {} install -y cockpit\nsudo systemctl enable --now cockpit.socket", ctx.package_manager) }
+    pub fn cockpit_full(ctx: &ScriptContext) -> String { format!("sudo {} install -y cockpit cockpit-machines cockpit-podman cockpit-storaged\nsudo systemctl enable --now cockpit.socket", ctx.package_manager) }
+    pub fn cockpit_storage(ctx: &ScriptContext) -> String { format!("sudo {} install -y cockpit-storaged", ctx.package_manager) }
+    pub fn cockpit_podman(ctx: &ScriptContext) -> String { format!("sudo {} install -y cockpit-podman", ctx.package_manager) }
+    pub fn cockpit_files(_ctx: &ScriptContext) -> String { "echo 'cockpit-files is part of the core cockpit package'".into() }
+    pub fn cockpit_image_builder(ctx: &ScriptContext) -> String { format!("sudo {} install -y cockpit-composer", ctx.package_manager) }
+    pub fn cockpit_machines(ctx: &ScriptContext) -> String { format!("sudo {} install -y cockpit-machines", ctx.package_manager) }
     
-    pub fn install_xen() -> &'static str { "sudo dnf install -y xen\nsudo systemctl enable xen-qemu-dom0-disk-backend.service" }
+    pub fn install_xen(ctx: &ScriptContext) -> String { format!("sudo {} install -y xen\nsudo systemctl enable xen-qemu-dom0-disk-backend.service", ctx.package_manager) }
+}
+
+mod scripts_containers {
+    use super::ScriptContext;
+
+    pub fn container_tools(ctx: &ScriptContext) -> String { format!("sudo {} install -y container-tools", ctx.package_manager) }
+    pub fn podman(ctx: &ScriptContext) -> String { format!("sudo {} install -y podman", ctx.package_manager) }
+    pub fn buildah(ctx: &ScriptContext) -> String { format!("sudo {} install -y buildah", ctx.package_manager) }
+    pub fn skopeo(ctx: &ScriptContext) -> String { format!("sudo {} install -y skopeo", ctx.package_manager) }
+    pub fn podman_socket(ctx: &ScriptContext) -> String {
+        format!("sudo {} install -y podman\nsudo systemctl enable --now podman.socket", ctx.package_manager)
+    }
+    // Gives the user who ran el-init (not root) a subordinate id range after the highest one in use.
+    pub fn rootless_setup(ctx: &ScriptContext) -> String {
+        format!("sudo {} install -y podman\n", ctx.package_manager) + r#"for MAP in subuid subgid; do
+    if ! grep -q "^$TARGET_USER:" "/etc/$MAP"; then
+        START=$(awk -F: '{ end = $2 + $3; if (end > max) max = end } END { print (max > 100000 ? max : 100000) }' "/etc/$MAP")
+        sudo usermod "--add-${MAP}s" "$START-$((START + 65535))" "$TARGET_USER"
+    fi
+done
+sudo -u "$TARGET_USER" podman system migrate"#
+    }
 }
 
 mod scripts_gnome {
-    pub fn base_install() -> &'static str { "sudo dnf install -y gdm gnome-shell gnome-terminal" }
-    pub fn full_install() -> &'static str { "sudo dnf groupinstall -y 'Workstation'" }
+    use super::ScriptContext;
+
+    pub fn base_install(ctx: &ScriptContext) -> String { format!("sudo {} install -y gdm gnome-shell gnome-terminal", ctx.package_manager) }
+    pub fn full_install(ctx: &ScriptContext) -> String { format!("sudo {} groupinstall -y 'Workstation'", ctx.package_manager) }
 }
 
 mod scripts_gnome_ext {
-    pub fn placeholder() -> &'static str { "echo 'GNOME Shell extension installation must be done manually or via a dedicated script.'" }
+    use super::ScriptContext;
+
+    pub fn placeholder(_ctx: &ScriptContext) -> String { "echo 'GNOME Shell extension installation must be done manually or via a dedicated script.'".into() }
 }
 
 mod scripts_gnome_apps {
-    pub fn konsole() -> &'static str { "sudo dnf install -y konsole" }
-    pub fn filezilla() -> &'static str { "sudo dnf install -y filezilla" }
-    pub fn remmina() -> &'static str { "sudo dnf install -y remmina" }
-    pub fn firefox() -> &'static str { "sudo dnf install -y firefox" }
-    pub fn chromium() -> &'static str { "sudo dnf install -y chromium" }
-    pub fn placeholder() -> &'static str { "echo 'This app is not in the default repos or requires special installation.'" }
+    use super::ScriptContext;
+
+    // These terminals are only packaged in COPR, so the install enables the COPR first.
+    pub fn ptyxis(ctx: &ScriptContext) -> String { format!("{}\nsudo {} install -y ptyxis", ctx.enable_copr("chergert/ptyxis"), ctx.package_manager) }
+    pub fn alacritty(ctx: &ScriptContext) -> String { format!("{}\nsudo {} install -y alacritty", ctx.enable_copr("atim/alacritty"), ctx.package_manager) }
+    pub fn ghostty(ctx: &ScriptContext) -> String { format!("{}\nsudo {} install -y ghostty", ctx.enable_copr("scottames/ghostty"), ctx.package_manager) }
+    pub fn default_ptyxis(_ctx: &ScriptContext) -> String { set_default_terminal("ptyxis", "org.gnome.Ptyxis.desktop") }
+    pub fn default_konsole(_ctx: &ScriptContext) -> String { set_default_terminal("konsole", "org.kde.konsole.desktop") }
+    pub fn default_alacritty(_ctx: &ScriptContext) -> String { set_default_terminal("alacritty", "Alacritty.desktop") }
+    pub fn default_ghostty(_ctx: &ScriptContext) -> String { set_default_terminal("ghostty", "com.mitchellh.ghostty.desktop") }
+
+    /// Sets the GNOME default terminal for the invoking user, both via the legacy gsettings key
+    /// and the xdg-terminal-exec list newer GNOME releases read.
+    fn set_default_terminal(exec: &str, desktop_id: &str) -> String {
+        format!(r#"TERMINAL_HOME="$(getent passwd "$TARGET_USER" | cut -d: -f6)"
+sudo -u "$TARGET_USER" dbus-run-session gsettings set org.gnome.desktop.default-applications.terminal exec '{exec}' || echo 'Could not set the gsettings default terminal key.'
+sudo -u "$TARGET_USER" mkdir -p "$TERMINAL_HOME/.config"
+echo '{desktop_id}' | sudo -u "$TARGET_USER" tee "$TERMINAL_HOME/.config/xdg-terminals.list" > /dev/null"#)
+    }
+
+    pub fn konsole(ctx: &ScriptContext) -> String { format!("sudo {} install -y konsole", ctx.package_manager) }
+    pub fn filezilla(ctx: &ScriptContext) -> String { format!("sudo {} install -y filezilla", ctx.package_manager) }
+    pub fn remmina(ctx: &ScriptContext) -> String { format!("sudo {} install -y remmina", ctx.package_manager) }
+    pub fn firefox(ctx: &ScriptContext) -> String { format!("sudo {} install -y firefox", ctx.package_manager) }
+    pub fn chromium(ctx: &ScriptContext) -> String { format!("sudo {} install -y chromium", ctx.package_manager) }
+}
+
+mod scripts_flatpak {
+    use super::ScriptContext;
+
+    // Flatpak apps come from the flathub remote, so select it under Repositories as well.
+    pub fn firefox(_ctx: &ScriptContext) -> String { "sudo flatpak install -y flathub org.mozilla.firefox".into() }
 }
 
 mod scripts_sway {
-    pub fn compile_from_source() -> &'static str { "echo 'Placeholder for Sway v1.10 compilation script'" }
-    pub fn install_wofi() -> &'static str { "sudo dnf install -y wofi" }
-    pub fn install_swaybg() -> &'static str { "sudo dnf install -y swaybg" }
-    pub fn install_waybar() -> &'static str { "sudo dnf install -y waybar" }
+    use super::ScriptContext;
+
+    pub fn compile_from_source(_ctx: &ScriptContext) -> String { "echo 'Placeholder for Sway v1.10 compilation script'".into() }
+    pub fn install_wofi(ctx: &ScriptContext) -> String { format!("sudo {} install -y wofi", ctx.package_manager) }
+    pub fn install_swaybg(ctx: &ScriptContext) -> String { format!("sudo {} install -y swaybg", ctx.package_manager) }
+    pub fn install_waybar(ctx: &ScriptContext) -> String { format!("sudo {} install -y waybar", ctx.package_manager) }
+    pub fn install_xwayland(ctx: &ScriptContext) -> String { format!("sudo {} install -y xorg-x11-server-Xwayland", ctx.package_manager) }
 }
 
 mod scripts_repos {
-    pub fn add_rt() -> &'static str { "sudo dnf config-manager --set-enabled rt" }
-    pub fn add_plus() -> &'static str { "sudo dnf config-manager --set-enabled plus" }
-    pub fn add_nfv() -> &'static str { "sudo dnf config-manager --set-enabled nfv" }
-    pub fn add_ha() -> &'static str { "sudo dnf config-manager --set-enabled ha" }
-    pub fn add_extras() -> &'static str { "sudo dnf config-manager --set-enabled extras" }
-    pub fn add_devel() -> &'static str { "sudo dnf config-manager --set-enabled devel" }
-    pub fn add_crb() -> &'static str { "sudo dnf config-manager --set-enabled crb" }
-    pub fn add_baseos() -> &'static str { "sudo dnf config-manager --set-enabled baseos" }
-    pub fn add_appstream() -> &'static str { "sudo dnf config-manager --set-enabled appstream" }
-    pub fn add_epel() -> &'static str { "sudo dnf config-manager --set-enabled crb\nsudo dnf install -y 'https://dl.fedoraproject.org/pub/epel/epel-release-latest-10.noarch.rpm'" }
-    pub fn add_flathub() -> &'static str { "sudo dnf install -y flatpak\nsudo flatpak remote-add --if-not-exists flathub https://dl.flathub.org/repo/flathub.flatpakrepo" }
+    use super::ScriptContext;
+
+    pub fn add_rt(ctx: &ScriptContext) -> String { ctx.enable_repo("rt") }
+    pub fn add_plus(ctx: &ScriptContext) -> String { ctx.enable_repo("plus") }
+    pub fn add_nfv(ctx: &ScriptContext) -> String { ctx.enable_repo("nfv") }
+    pub fn add_ha(ctx: &ScriptContext) -> String { ctx.enable_repo("ha") }
+    pub fn add_extras(ctx: &ScriptContext) -> String { ctx.enable_repo("extras") }
+    pub fn add_ha_stream(ctx: &ScriptContext) -> String { ctx.enable_repo("highavailability") }
+    pub fn add_extras_stream(ctx: &ScriptContext) -> String { ctx.enable_repo("extras-common") }
+    pub fn add_devel(ctx: &ScriptContext) -> String { ctx.enable_repo("devel") }
+    pub fn add_crb(ctx: &ScriptContext) -> String { ctx.enable_repo("crb") }
+    pub fn add_baseos(ctx: &ScriptContext) -> String { ctx.enable_repo("baseos") }
+    pub fn add_appstream(ctx: &ScriptContext) -> String { ctx.enable_repo("appstream") }
+    pub fn add_epel(ctx: &ScriptContext) -> String {
+        format!("sudo {} install -y 'https://dl.fedoraproject.org/pub/epel/epel-release-latest-{}.noarch.rpm'", ctx.package_manager, ctx.epel_version)
+    }
+    pub fn add_flathub(ctx: &ScriptContext) -> String {
+        format!("sudo {} install -y flatpak\nsudo flatpak remote-add --if-not-exists flathub {}", ctx.package_manager, ctx.flathub_url)
+    }
+}
+
+// Repo ids are built at run time so one script covers every RHEL major version and arch.
+mod scripts_rhel_repos {
+    use super::ScriptContext;
+
+    pub fn add_rt(_ctx: &ScriptContext) -> String { r#"sudo subscription-manager repos --enable "rhel-$(rpm -E %rhel)-for-$(arch)-rt-rpms""#.into() }
+    pub fn add_nfv(_ctx: &ScriptContext) -> String { r#"sudo subscription-manager repos --enable "rhel-$(rpm -E %rhel)-for-$(arch)-nfv-rpms""#.into() }
+    pub fn add_ha(_ctx: &ScriptContext) -> String { r#"sudo subscription-manager repos --enable "rhel-$(rpm -E %rhel)-for-$(arch)-highavailability-rpms""#.into() }
+    pub fn add_crb(_ctx: &ScriptContext) -> String { r#"sudo subscription-manager repos --enable "codeready-builder-for-rhel-$(rpm -E %rhel)-$(arch)-rpms""#.into() }
+    pub fn add_baseos(_ctx: &ScriptContext) -> String { r#"sudo subscription-manager repos --enable "rhel-$(rpm -E %rhel)-for-$(arch)-baseos-rpms""#.into() }
+    pub fn add_appstream(_ctx: &ScriptContext) -> String { r#"sudo subscription-manager repos --enable "rhel-$(rpm -E %rhel)-for-$(arch)-appstream-rpms""#.into() }
+    pub fn add_epel(ctx: &ScriptContext) -> String {
+        format!("sudo {} install -y 'https://dl.fedoraproject.org/pub/epel/epel-release-latest-{}.noarch.rpm'", ctx.package_manager, ctx.epel_version)
+    }
+}
+
+mod scripts_copr {
+    use super::ScriptContext;
+
+    pub fn enable_ptyxis(ctx: &ScriptContext) -> String { ctx.enable_copr("chergert/ptyxis") }
+    pub fn enable_alacritty(ctx: &ScriptContext) -> String { ctx.enable_copr("atim/alacritty") }
+    pub fn enable_ghostty(ctx: &ScriptContext) -> String { ctx.enable_copr("scottames/ghostty") }
 }
 
 mod scripts_net {
-    pub fn install_vpn_ovpn() -> &'static str { "sudo dnf install -y NetworkManager-openvpn-gnome" }
-    pub fn install_vpn_l2tp() -> &'static str { "sudo dnf install -y NetworkManager-l2tp-gnome" }
-    pub fn install_vpn_sswan() -> &'static str { "sudo dnf install -y NetworkManager-strongswan-gnome" }
-    pub fn install_vpn_lswan() -> &'static str { "sudo dnf install -y NetworkManager-libreswan-gnome" }
-    pub fn install_vpn_pptp() -> &'static str { "sudo dnf install -y NetworkManager-pptp-gnome" }
-    pub fn install_vpn_oconn() -> &'static str { "sudo dnf install -y NetworkManager-openconnect-gnome" }
+    use super::ScriptContext;
+
+    pub fn install_vpn_ovpn(ctx: &ScriptContext) -> String { format!("sudo {} install -y NetworkManager-openvpn-gnome", ctx.package_manager) }
+    pub fn install_vpn_l2tp(ctx: &ScriptContext) -> String { format!("sudo {} install -y NetworkManager-l2tp-gnome", ctx.package_manager) }
+    pub fn install_vpn_sswan(ctx: &ScriptContext) -> String { format!("sudo {} install -y NetworkManager-strongswan-gnome", ctx.package_manager) }
+    pub fn install_vpn_lswan(ctx: &ScriptContext) -> String { format!("sudo {} install -y NetworkManager-libreswan-gnome", ctx.package_manager) }
+    pub fn install_vpn_pptp(ctx: &ScriptContext) -> String { format!("sudo {} install -y NetworkManager-pptp-gnome", ctx.package_manager) }
+    pub fn install_vpn_oconn(ctx: &ScriptContext) -> String { format!("sudo {} install -y NetworkManager-openconnect-gnome", ctx.package_manager) }
 }
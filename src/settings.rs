@@ -0,0 +1,153 @@
+// src/settings.rs
+
+use crate::input::TextInput;
+use crate::scripts::ScriptContext;
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+const EPEL_VERSION: usize = 0;
+const FLATHUB_URL: usize = 1;
+const PARALLEL_DOWNLOADS: usize = 2;
+// Focus indices of the toggles, which follow the text fields.
+const KVM_GROUP_ADD: usize = 3;
+const BACKUP_CONFIGS: usize = 4;
+const RECORD_RUN: usize = 5;
+const ALLOW_ERASING: usize = 6;
+const NOBEST: usize = 7;
+const SYNTAX_HIGHLIGHT: usize = 8;
+
+/// Form for overriding the external references baked into the repository scripts.
+pub struct SettingsForm {
+    fields: [TextInput; 3],
+    kvm_group_add: bool,
+    backup_configs: bool,
+    record_run: bool,
+    allow_erasing: bool,
+    nobest: bool,
+    syntax_highlight: bool,
+    focus: usize,
+    pub error: Option<String>,
+}
+
+impl SettingsForm {
+    /// Creates a form pre-filled with the current script context, dnf install flags and display options.
+    pub fn from_context(ctx: &ScriptContext, syntax_highlight: bool, allow_erasing: bool, nobest: bool) -> SettingsForm {
+        SettingsForm {
+            fields: [
+                TextInput::new(ctx.epel_version.to_string()),
+                TextInput::new(ctx.flathub_url.clone()),
+                TextInput::new(ctx.parallel_downloads.map(|n| n.to_string()).unwrap_or_default()),
+            ],
+            kvm_group_add: ctx.kvm_group_add,
+            backup_configs: ctx.backup_configs,
+            record_run: ctx.record_run,
+            allow_erasing,
+            nobest,
+            syntax_highlight,
+            focus: EPEL_VERSION,
+            error: None,
+        }
+    }
+
+    /// Moves focus between fields, toggles the focused switch with Space, or forwards the
+    /// key to the focused text field.
+    pub fn handle_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Tab | KeyCode::Down | KeyCode::Up | KeyCode::BackTab => {
+                self.focus = (self.focus + 1) % (SYNTAX_HIGHLIGHT + 1);
+            }
+            KeyCode::Char(' ') if self.focus == KVM_GROUP_ADD => self.kvm_group_add = !self.kvm_group_add,
+            KeyCode::Char(' ') if self.focus == BACKUP_CONFIGS => self.backup_configs = !self.backup_configs,
+            KeyCode::Char(' ') if self.focus == RECORD_RUN => self.record_run = !self.record_run,
+            KeyCode::Char(' ') if self.focus == ALLOW_ERASING => self.allow_erasing = !self.allow_erasing,
+            KeyCode::Char(' ') if self.focus == NOBEST => self.nobest = !self.nobest,
+            KeyCode::Char(' ') if self.focus == SYNTAX_HIGHLIGHT => self.syntax_highlight = !self.syntax_highlight,
+            _ if self.focus >= self.fields.len() => {}
+            code => {
+                self.fields[self.focus].handle_key(code);
+            }
+        }
+    }
+
+    /// Validates the fields and writes them into the script context.
+    pub fn apply(&self, ctx: &mut ScriptContext) -> Result<(), String> {
+        let epel = self.fields[EPEL_VERSION].value.trim();
+        let epel_version = epel.parse::<u32>()
+            .map_err(|_| format!("EPEL version must be a number, got '{}'", epel))?;
+        let flathub_url = self.fields[FLATHUB_URL].value.trim();
+        if flathub_url.is_empty() {
+            return Err("Flathub remote URL cannot be empty".to_string());
+        }
+
+        let parallel = self.fields[PARALLEL_DOWNLOADS].value.trim();
+        let parallel_downloads = match parallel {
+            "" => None,
+            value => match value.parse::<u32>() {
+                Ok(n @ 1..=20) => Some(n),
+                _ => return Err(format!("Parallel downloads must be 1-20 or empty, got '{}'", value)),
+            },
+        };
+
+        ctx.epel_version = epel_version;
+        ctx.flathub_url = flathub_url.to_string();
+        ctx.parallel_downloads = parallel_downloads;
+        ctx.kvm_group_add = self.kvm_group_add;
+        ctx.backup_configs = self.backup_configs;
+        ctx.record_run = self.record_run;
+        Ok(())
+    }
+
+    /// Whether dnf installs get `--allowerasing`.
+    pub fn allow_erasing(&self) -> bool {
+        self.allow_erasing
+    }
+
+    /// Whether dnf installs get `--nobest`.
+    pub fn nobest(&self) -> bool {
+        self.nobest
+    }
+
+    /// Whether script previews should be syntax highlighted.
+    pub fn syntax_highlight(&self) -> bool {
+        self.syntax_highlight
+    }
+}
+
+pub fn draw_settings_popup(f: &mut Frame, form: &SettingsForm, area: Rect) {
+    let block = Block::default().title("Settings").borders(Borders::ALL);
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default().direction(Direction::Vertical).margin(2)
+        .constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Length(3), Constraint::Length(1), Constraint::Length(1), Constraint::Length(1), Constraint::Length(1), Constraint::Length(1), Constraint::Length(1), Constraint::Length(1), Constraint::Min(1)].as_ref())
+        .split(area);
+
+    f.render_widget(form.fields[EPEL_VERSION].widget("EPEL major version", form.focus == EPEL_VERSION), chunks[0]);
+    f.render_widget(form.fields[FLATHUB_URL].widget("Flathub remote URL", form.focus == FLATHUB_URL), chunks[1]);
+    let parallel_title = "dnf parallel downloads (empty = leave dnf.conf alone)";
+    f.render_widget(form.fields[PARALLEL_DOWNLOADS].widget(parallel_title, form.focus == PARALLEL_DOWNLOADS), chunks[2]);
+    let toggles = [
+        (KVM_GROUP_ADD, form.kvm_group_add, "Add the user to libvirt/kvm after KVM installs"),
+        (BACKUP_CONFIGS, form.backup_configs, "Back up config files before steps that modify them"),
+        (RECORD_RUN, form.record_run, "Record step names, exit codes and durations to last-run.json"),
+        (ALLOW_ERASING, form.allow_erasing, "dnf install --allowerasing (replace conflicting packages)"),
+        (NOBEST, form.nobest, "dnf install --nobest (accept older versions when the newest conflict)"),
+        (SYNTAX_HIGHLIGHT, form.syntax_highlight, "Highlight bash syntax in script previews"),
+    ];
+    for (row, (focus, on, label)) in toggles.into_iter().enumerate() {
+        let text = format!("[{}] {}", if on { "x" } else { " " }, label);
+        let style = if form.focus == focus { Style::default().fg(Color::Yellow) } else { Style::default() };
+        f.render_widget(Paragraph::new(text).style(style), chunks[3 + row]);
+    }
+
+    let hint = match &form.error {
+        Some(err) => Paragraph::new(err.as_str()).style(Style::default().fg(Color::Red)),
+        None => Paragraph::new("[Tab] Next field | [Space] Toggle | [Enter] Apply | [Esc] Cancel"),
+    };
+    f.render_widget(hint, chunks[9]);
+}
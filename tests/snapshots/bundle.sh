@@ -0,0 +1,110 @@
+#!/bin/bash
+# Generated for all supported EL distros by Enterprise Linux TUI
+# Package manager: dnf
+
+# Exit immediately if a command exits with a non-zero status.
+set -e
+
+# The user who invoked the script, not root when it runs through sudo.
+TARGET_USER="${SUDO_USER:-$USER}"
+
+# Helper for logging steps
+print_step() {
+    echo
+    echo "✅ ==> $1"
+}
+
+# --- 1. ENABLING REPOSITORIES ---
+# CentOS Stream shares ID=centos with CentOS Linux; only NAME tells them apart.
+. /etc/os-release
+EL_DISTRO="$ID"
+if [ "$ID" = centos ] && [[ "$NAME" == *Stream* ]]; then EL_DISTRO=centos-stream; fi
+case "$EL_DISTRO" in
+rhel)
+print_step "Backing up config files (<file>.el-init.bak)"
+for file in /var/lib/flatpak/repo/config /etc/yum.repos.d/*.repo; do
+    if [ -e "$file" ]; then sudo cp --backup=numbered "$file" "$file.el-init.bak"; fi
+done
+# config-manager and copr come from dnf-plugins-core, which minimal installs lack.
+rpm -q dnf-plugins-core > /dev/null 2>&1 || sudo dnf install -y dnf-plugins-core
+# + auto-added: Repositories > Add Repositories (ROCKY LINUX SPECIFIC) > flathub
+print_step "flathub"
+sudo dnf install -y flatpak
+sudo flatpak remote-add --if-not-exists flathub https://dl.flathub.org/repo/flathub.flatpakrepo
+print_step "CRB (code ready builder)"
+sudo subscription-manager repos --enable "codeready-builder-for-rhel-$(rpm -E %rhel)-$(arch)-rpms"
+print_step "epel"
+sudo dnf install -y 'https://dl.fedoraproject.org/pub/epel/epel-release-latest-9.noarch.rpm'
+print_step "High availibility"
+sudo subscription-manager repos --enable "rhel-$(rpm -E %rhel)-for-$(arch)-highavailability-rpms"
+print_step "chergert/ptyxis"
+sudo dnf copr enable -y chergert/ptyxis
+;;
+centos|rocky|almalinux)
+print_step "Backing up config files (<file>.el-init.bak)"
+for file in /var/lib/flatpak/repo/config /etc/yum.repos.d/*.repo; do
+    if [ -e "$file" ]; then sudo cp --backup=numbered "$file" "$file.el-init.bak"; fi
+done
+# config-manager and copr come from dnf-plugins-core, which minimal installs lack.
+rpm -q dnf-plugins-core > /dev/null 2>&1 || sudo dnf install -y dnf-plugins-core
+# + auto-added: Repositories > Add Repositories (ROCKY LINUX SPECIFIC) > flathub
+print_step "flathub"
+sudo dnf install -y flatpak
+sudo flatpak remote-add --if-not-exists flathub https://dl.flathub.org/repo/flathub.flatpakrepo
+print_step "CRB (code ready builder)"
+sudo dnf config-manager --set-enabled crb
+print_step "epel"
+sudo dnf install -y 'https://dl.fedoraproject.org/pub/epel/epel-release-latest-9.noarch.rpm'
+print_step "High availibility"
+sudo dnf config-manager --set-enabled ha
+print_step "chergert/ptyxis"
+sudo dnf copr enable -y chergert/ptyxis
+;;
+centos-stream)
+print_step "Backing up config files (<file>.el-init.bak)"
+for file in /var/lib/flatpak/repo/config /etc/yum.repos.d/*.repo; do
+    if [ -e "$file" ]; then sudo cp --backup=numbered "$file" "$file.el-init.bak"; fi
+done
+# config-manager and copr come from dnf-plugins-core, which minimal installs lack.
+rpm -q dnf-plugins-core > /dev/null 2>&1 || sudo dnf install -y dnf-plugins-core
+# + auto-added: Repositories > Add Repositories (ROCKY LINUX SPECIFIC) > flathub
+print_step "flathub"
+sudo dnf install -y flatpak
+sudo flatpak remote-add --if-not-exists flathub https://dl.flathub.org/repo/flathub.flatpakrepo
+print_step "CRB (code ready builder)"
+sudo dnf config-manager --set-enabled crb
+print_step "epel"
+sudo dnf install -y 'https://dl.fedoraproject.org/pub/epel/epel-release-latest-9.noarch.rpm'
+print_step "High availibility"
+sudo dnf config-manager --set-enabled highavailability
+print_step "chergert/ptyxis"
+sudo dnf copr enable -y chergert/ptyxis
+;;
+*)
+echo "No repository steps for '$EL_DISTRO'; supported: rhel, centos, centos-stream, rocky, almalinux" >&2
+exit 1
+;;
+esac
+# --- END OF REPOSITORY PHASE ---
+
+# --- 2. APPLYING CONFIGURATIONS ---
+print_step "Firefox (Flatpak)"
+sudo flatpak install -y flathub org.mozilla.firefox
+print_step "Ptyxis"
+sudo dnf copr enable -y chergert/ptyxis
+sudo dnf install -y ptyxis
+print_step "Ptyxis"
+TERMINAL_HOME="$(getent passwd "$TARGET_USER" | cut -d: -f6)"
+sudo -u "$TARGET_USER" dbus-run-session gsettings set org.gnome.desktop.default-applications.terminal exec 'ptyxis' || echo 'Could not set the gsettings default terminal key.'
+sudo -u "$TARGET_USER" mkdir -p "$TERMINAL_HOME/.config"
+echo 'org.gnome.Ptyxis.desktop' | sudo -u "$TARGET_USER" tee "$TERMINAL_HOME/.config/xdg-terminals.list" > /dev/null
+print_step "Base Installation"
+sudo dnf install -y qemu-kvm libvirt-daemon-config-network libvirt-daemon-kvm edk2-ovmf
+for group in libvirt kvm; do
+    if [ "$TARGET_USER" != root ] && ! id -nG "$TARGET_USER" | grep -qw "$group"; then
+        sudo usermod -aG "$group" "$TARGET_USER"
+        echo "Added $TARGET_USER to the $group group; log out and back in to manage VMs without sudo."
+    fi
+done
+
+print_step "All tasks complete!"
@@ -0,0 +1,17 @@
+#!/bin/bash
+# Generated for Rocky by Enterprise Linux TUI
+# Package manager: dnf
+
+# Exit immediately if a command exits with a non-zero status.
+set -e
+
+# The user who invoked the script, not root when it runs through sudo.
+TARGET_USER="${SUDO_USER:-$USER}"
+
+# Helper for logging steps
+print_step() {
+    echo
+    echo "✅ ==> $1"
+}
+
+# No options selected.
@@ -0,0 +1,32 @@
+#!/bin/bash
+# Generated for Rocky by Enterprise Linux TUI
+# Package manager: dnf
+
+# Exit immediately if a command exits with a non-zero status.
+set -e
+
+# The user who invoked the script, not root when it runs through sudo.
+TARGET_USER="${SUDO_USER:-$USER}"
+
+# Helper for logging steps
+print_step() {
+    echo
+    echo "✅ ==> $1"
+}
+
+# --- 1. ENABLING REPOSITORIES ---
+print_step "Backing up config files (<file>.el-init.bak)"
+for file in /etc/yum.repos.d/*.repo; do
+    if [ -e "$file" ]; then sudo cp --backup=numbered "$file" "$file.el-init.bak"; fi
+done
+# config-manager and copr come from dnf5-plugins, which minimal installs lack.
+rpm -q dnf5-plugins > /dev/null 2>&1 || sudo dnf install -y dnf5-plugins
+print_step "CRB (code ready builder)"
+sudo dnf config-manager setopt crb.enabled=1
+print_step "epel"
+sudo dnf install -y 'https://dl.fedoraproject.org/pub/epel/epel-release-latest-10.noarch.rpm'
+print_step "High availibility"
+sudo dnf config-manager setopt ha.enabled=1
+# --- END OF REPOSITORY PHASE ---
+
+print_step "All tasks complete!"
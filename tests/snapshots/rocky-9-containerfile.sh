@@ -0,0 +1,58 @@
+# syntax=docker/dockerfile:1
+# Generated for Rocky by Enterprise Linux TUI
+FROM docker.io/rockylinux/rockylinux:9
+ARG TARGET_USER=root
+
+RUN dnf install -y dnf-plugins-core
+
+# Repositories > Add Repositories (ROCKY LINUX SPECIFIC) > flathub
+RUN <<'EOF'
+set -e
+dnf install -y flatpak
+flatpak remote-add --if-not-exists flathub https://dl.flathub.org/repo/flathub.flatpakrepo
+EOF
+
+# Repositories > Add Repositories (ROCKY LINUX SPECIFIC) > CRB (code ready builder)
+RUN dnf config-manager --set-enabled crb
+
+# Repositories > Add Repositories (ROCKY LINUX SPECIFIC) > epel
+RUN dnf install -y 'https://dl.fedoraproject.org/pub/epel/epel-release-latest-9.noarch.rpm'
+
+# Repositories > Add Repositories (ROCKY LINUX SPECIFIC) > High availibility
+RUN dnf config-manager --set-enabled ha
+
+# Repositories > COPR Repositories > chergert/ptyxis
+RUN dnf copr enable -y chergert/ptyxis
+
+# Graphical Environments > Gnome DE - STABLE > Applications / Packages > Browsers > Firefox (Flatpak)
+RUN flatpak install -y flathub org.mozilla.firefox
+
+# Graphical Environments > Gnome DE - STABLE > Applications / Packages > Terminals > Ptyxis
+RUN <<'EOF'
+set -e
+dnf copr enable -y chergert/ptyxis
+dnf install -y ptyxis
+EOF
+
+# Graphical Environments > Gnome DE - STABLE > Applications / Packages > Terminals > Default Terminal > Ptyxis
+RUN <<'EOF'
+set -e
+TERMINAL_HOME="$(getent passwd "$TARGET_USER" | cut -d: -f6)"
+dbus-run-session gsettings set org.gnome.desktop.default-applications.terminal exec 'ptyxis' || echo 'Could not set the gsettings default terminal key.'
+mkdir -p "$TERMINAL_HOME/.config"
+echo 'org.gnome.Ptyxis.desktop' | tee "$TERMINAL_HOME/.config/xdg-terminals.list" > /dev/null
+EOF
+
+# Virtualization > Virtualization Engines > KVM Core & Tools > Base Installation
+RUN <<'EOF'
+set -e
+dnf install -y qemu-kvm libvirt-daemon-config-network libvirt-daemon-kvm edk2-ovmf
+for group in libvirt kvm; do
+    if [ "$TARGET_USER" != root ] && ! id -nG "$TARGET_USER" | grep -qw "$group"; then
+        usermod -aG "$group" "$TARGET_USER"
+        echo "Added $TARGET_USER to the $group group; log out and back in to manage VMs without sudo."
+    fi
+done
+EOF
+
+RUN dnf clean all
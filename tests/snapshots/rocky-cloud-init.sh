@@ -0,0 +1,26 @@
+#cloud-config
+# Generated for Rocky by Enterprise Linux TUI
+runcmd:
+  - "dnf install -y dnf-plugins-core"
+  # Repositories > Add Repositories (ROCKY LINUX SPECIFIC) > flathub
+  - [bash, -ec, "dnf install -y flatpak\nflatpak remote-add --if-not-exists flathub https://dl.flathub.org/repo/flathub.flatpakrepo"]
+  # Repositories > Add Repositories (ROCKY LINUX SPECIFIC) > CRB (code ready builder)
+  - "dnf config-manager --set-enabled crb"
+  # Repositories > Add Repositories (ROCKY LINUX SPECIFIC) > epel
+  - "dnf install -y 'https://dl.fedoraproject.org/pub/epel/epel-release-latest-9.noarch.rpm'"
+  # Repositories > Add Repositories (ROCKY LINUX SPECIFIC) > High availibility
+  - "dnf config-manager --set-enabled ha"
+  # Repositories > COPR Repositories > chergert/ptyxis
+  - "dnf copr enable -y chergert/ptyxis"
+  # Graphical Environments > Gnome DE - STABLE > Applications / Packages > Browsers > Firefox (Flatpak)
+  - "flatpak install -y flathub org.mozilla.firefox"
+  # Graphical Environments > Gnome DE - STABLE > Applications / Packages > Terminals > Ptyxis
+  - [bash, -ec, "dnf copr enable -y chergert/ptyxis\ndnf install -y ptyxis"]
+  # Graphical Environments > Gnome DE - STABLE > Applications / Packages > Terminals > Default Terminal > Ptyxis
+  - [bash, -ec, "TARGET_USER=root\nTERMINAL_HOME=\"$(getent passwd \"$TARGET_USER\" | cut -d: -f6)\"\ndbus-run-session gsettings set org.gnome.desktop.default-applications.terminal exec 'ptyxis' || echo 'Could not set the gsettings default terminal key.'\nmkdir -p \"$TERMINAL_HOME/.config\"\necho 'org.gnome.Ptyxis.desktop' | tee \"$TERMINAL_HOME/.config/xdg-terminals.list\" > /dev/null"]
+  # Virtualization > Virtualization Engines > KVM Core & Tools > Base Installation
+  - [bash, -ec, "TARGET_USER=root\ndnf install -y qemu-kvm libvirt-daemon-config-network libvirt-daemon-kvm edk2-ovmf\nfor group in libvirt kvm; do\n    if [ \"$TARGET_USER\" != root ] && ! id -nG \"$TARGET_USER\" | grep -qw \"$group\"; then\n        usermod -aG \"$group\" \"$TARGET_USER\"\n        echo \"Added $TARGET_USER to the $group group; log out and back in to manage VMs without sudo.\"\n    fi\ndone"]
+power_state:
+  mode: reboot
+  message: "el-init: rebooting to finish setup"
+  condition: true